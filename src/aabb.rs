@@ -70,7 +70,13 @@ impl AABB {
     }
 
     #[allow(dead_code)]
-    pub fn hit(&self, ray: &Ray, mut ray_t: Interval) -> bool {
+    pub fn hit(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.clip(ray, ray_t).is_some()
+    }
+
+    #[allow(dead_code)]
+    pub fn clip(&self, ray: &Ray, mut ray_t: Interval) -> Option<Interval> {
+        // The part of the ray interval inside the box, if any
         let ray_origin = &ray.origin;
         let ray_dir = &ray.dir;
 
@@ -83,17 +89,25 @@ impl AABB {
 
             if t0 < t1 {
                 ray_t.min = ray_t.min.max(t0);
-                ray_t.max = ray_t.min.min(t1);
+                ray_t.max = ray_t.max.min(t1);
             } else {
                 ray_t.min = ray_t.min.max(t1);
                 ray_t.max = ray_t.max.min(t0);
             }
 
             if ray_t.max <= ray_t.min {
-                return false;
+                return None;
             }
         }
-        true
+        Some(ray_t)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_bounded(&self) -> bool {
+        // False for the boxes of infinite objects like planes, which reach out to infinity
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|axis| axis.min.is_finite() && axis.max.is_finite())
     }
 
     #[allow(dead_code)]
@@ -119,7 +133,7 @@ impl AABB {
     fn pad_to_minimums(&mut self) {
         // Adjust the AABB so that no side is narrower than some delta, padding if necessary
 
-        let delta = 0.0;
+        let delta = 0.0001;
         if self.x.size() < delta {
             self.x = self.x.expand(delta);
         }
@@ -0,0 +1,263 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    exporter::Exporter, film::Film, progress::SilentProgress, scenes, stats, utils::seed_rng,
+};
+
+// Settings for one benchmark run. Scenes are rendered at a fixed size and sample count from
+// fixed seeds, so the same work is done on every run.
+pub struct BenchScene {
+    pub name: &'static str,
+    pub image_width: i32,
+    pub samples_per_pixel: i32,
+    pub max_depth: i32,
+}
+
+pub const BENCH_SCENES: [BenchScene; 5] = [
+    BenchScene {
+        name: "final_scene",
+        image_width: 200,
+        samples_per_pixel: 16,
+        max_depth: 8,
+    },
+    BenchScene {
+        name: "cornell_box",
+        image_width: 200,
+        samples_per_pixel: 16,
+        max_depth: 20,
+    },
+    BenchScene {
+        name: "cornell_smoke",
+        image_width: 200,
+        samples_per_pixel: 16,
+        max_depth: 20,
+    },
+    BenchScene {
+        name: "perlin_spheres",
+        image_width: 200,
+        samples_per_pixel: 16,
+        max_depth: 10,
+    },
+    BenchScene {
+        name: "earth",
+        image_width: 200,
+        samples_per_pixel: 16,
+        max_depth: 10,
+    },
+];
+
+const SEED: u64 = 1;
+
+const CSV_HEADER: &str = "label,scene,rays,rays_per_second,render_seconds,bvh_build_seconds,bvh_nodes,bvh_bytes,peak_heap_bytes";
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub label: String,
+    pub scene: String,
+    pub rays: u64,
+    pub rays_per_second: f64,
+    pub render_seconds: f64,
+    pub bvh_build_seconds: f64,
+    pub bvh_nodes: u64,
+    pub bvh_bytes: u64,
+    pub peak_heap_bytes: u64,
+}
+
+impl BenchResult {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{:.1},{:.6},{:.6},{},{},{}",
+            self.label,
+            self.scene,
+            self.rays,
+            self.rays_per_second,
+            self.render_seconds,
+            self.bvh_build_seconds,
+            self.bvh_nodes,
+            self.bvh_bytes,
+            self.peak_heap_bytes,
+        )
+    }
+
+    fn from_csv(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 9 {
+            return None;
+        }
+        Some(Self {
+            label: fields[0].to_string(),
+            scene: fields[1].to_string(),
+            rays: fields[2].parse().ok()?,
+            rays_per_second: fields[3].parse().ok()?,
+            render_seconds: fields[4].parse().ok()?,
+            bvh_build_seconds: fields[5].parse().ok()?,
+            bvh_nodes: fields[6].parse().ok()?,
+            bvh_bytes: fields[7].parse().ok()?,
+            peak_heap_bytes: fields[8].parse().ok()?,
+        })
+    }
+}
+
+// The benchmark only looks at the statistics, the image itself is thrown away
+struct NullExporter;
+
+impl Exporter for NullExporter {
+    fn export(&mut self, _film: &Film) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn run_scene(bench: &BenchScene, label: &str, runs: u32) -> BenchResult {
+    // Renders the scene `runs` times and keeps the run with the median ray throughput
+    let mut results: Vec<BenchResult> = (0..runs.max(1))
+        .map(|_| {
+            let heap_before = stats::heap_bytes();
+            stats::reset_peak_heap();
+
+            seed_rng(SEED);
+            let mut scene = scenes::by_name(bench.name, Box::new(NullExporter))
+                .expect("Benchmark scenes should all exist");
+            scene.camera.image_width = bench.image_width;
+            scene.camera.samples_per_pixel = bench.samples_per_pixel;
+            scene.camera.max_depth = bench.max_depth;
+            scene.camera.progress = Box::new(SilentProgress);
+            scene.camera.print_stats = false;
+            scene.render();
+
+            let render_stats = scene.camera.stats();
+            BenchResult {
+                label: label.to_string(),
+                scene: bench.name.to_string(),
+                rays: render_stats.total_rays(),
+                rays_per_second: render_stats.rays_per_second(),
+                render_seconds: render_stats.render_time.as_secs_f64(),
+                bvh_build_seconds: render_stats.bvh_build_time.as_secs_f64(),
+                bvh_nodes: render_stats.bvh_nodes,
+                bvh_bytes: render_stats.bvh_bytes,
+                peak_heap_bytes: stats::peak_heap_bytes().saturating_sub(heap_before) as u64,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| a.rays_per_second.total_cmp(&b.rays_per_second));
+    results.swap_remove(results.len() / 2)
+}
+
+pub fn append_results(path: &Path, results: &[BenchResult]) -> io::Result<()> {
+    // Results accumulate in one CSV file, so a history of runs (one label per commit, say) can
+    // be kept and compared against
+    let new_file = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new_file {
+        writeln!(file, "{CSV_HEADER}")?;
+    }
+    for result in results {
+        writeln!(file, "{}", result.to_csv())?;
+    }
+    Ok(())
+}
+
+pub fn read_results(path: &Path) -> io::Result<Vec<BenchResult>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            BenchResult::from_csv(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Bad benchmark result line in {}: {line}", path.display()),
+                )
+            })
+        })
+        .collect()
+}
+
+pub fn format_table(results: &[BenchResult]) -> String {
+    let mut table = format!(
+        "{:<16} {:>12} {:>10} {:>12} {:>10} {:>12}\n",
+        "scene", "Mrays/s", "render s", "BVH build ms", "BVH KiB", "peak MiB"
+    );
+    for result in results {
+        let _ = writeln!(
+            table,
+            "{:<16} {:>12.3} {:>10.3} {:>12.3} {:>10.1} {:>12.2}",
+            result.scene,
+            result.rays_per_second / 1e6,
+            result.render_seconds,
+            result.bvh_build_seconds * 1e3,
+            result.bvh_bytes as f64 / 1024.0,
+            result.peak_heap_bytes as f64 / (1024.0 * 1024.0),
+        );
+    }
+    table
+}
+
+pub fn regressions(
+    previous: &[BenchResult],
+    current: &[BenchResult],
+    threshold: f64,
+) -> Vec<String> {
+    // Compares each scene against its most recent previous result. Lower throughput, or slower
+    // BVH builds or higher memory use, by more than `threshold` (a fraction) is flagged. BVH
+    // builds under a millisecond are too noisy to compare.
+    let mut flagged = Vec::new();
+    for result in current {
+        let Some(before) = previous
+            .iter()
+            .rev()
+            .find(|before| before.scene == result.scene)
+        else {
+            continue;
+        };
+
+        let mut check = |what: &str, before_value: f64, value: f64, higher_is_better: bool| {
+            if before_value <= 0.0 || (what == "BVH build ms" && before_value < 1.0) {
+                return;
+            }
+            let change = (value - before_value) / before_value;
+            let worse = if higher_is_better { -change } else { change };
+            if worse > threshold {
+                flagged.push(format!(
+                    "{}: {what} {:+.1}% ({:.1} -> {:.1}, compared to {})",
+                    result.scene,
+                    change * 100.0,
+                    before_value,
+                    value,
+                    before.label,
+                ));
+            }
+        };
+        check(
+            "rays/s",
+            before.rays_per_second,
+            result.rays_per_second,
+            true,
+        );
+        check(
+            "BVH build ms",
+            before.bvh_build_seconds * 1e3,
+            result.bvh_build_seconds * 1e3,
+            false,
+        );
+        check(
+            "BVH memory",
+            before.bvh_bytes as f64,
+            result.bvh_bytes as f64,
+            false,
+        );
+        check(
+            "peak heap",
+            before.peak_heap_bytes as f64,
+            result.peak_heap_bytes as f64,
+            false,
+        );
+    }
+    flagged
+}
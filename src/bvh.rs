@@ -1,10 +1,11 @@
-use std::{cmp::Ordering, rc::Rc};
+use std::{cmp::Ordering, mem, rc::Rc, time::Instant};
 
 use crate::{
     aabb::AABB,
     hittable::{HitRecord, Hittable, HittableList},
     interval::Interval,
     ray::Ray,
+    stats,
     utils::random_int_range,
 };
 
@@ -18,15 +19,52 @@ pub struct BVHNode {
 impl BVHNode {
     #[allow(dead_code)]
     pub fn from_list(mut list: HittableList) -> Self {
-        let len = list.objects.len();
-        Self::new(&mut list.objects, 0, len)
+        let start = Instant::now();
+
+        // Unbounded objects (infinite planes) would stretch every box above them to infinity,
+        // so they stay out of the tree and are tested alongside it under a root with no bounds
+        let (mut bounded, unbounded): (Vec<_>, Vec<_>) = list
+            .objects
+            .drain(..)
+            .partition(|object| object.bounding_box().is_bounded());
+
+        let len = bounded.len();
+        let (node, nodes) = if unbounded.is_empty() {
+            (Self::new(&mut bounded, 0, len), Self::node_count(len))
+        } else {
+            let mut others = HittableList::default();
+            for object in unbounded {
+                others.add(object);
+            }
+            let others = Rc::new(others) as Rc<dyn Hittable>;
+            let (tree, tree_nodes) = if bounded.is_empty() {
+                // Nothing to build a tree over. An empty list on that side, rather than the
+                // unbounded ones again, so each of them is only tested once per ray.
+                (Rc::new(HittableList::default()) as Rc<dyn Hittable>, 0)
+            } else {
+                let tree = Rc::new(Self::new(&mut bounded, 0, len)) as Rc<dyn Hittable>;
+                (tree, Self::node_count(len))
+            };
+            let root = Self {
+                left: tree,
+                right: others,
+                bbox: AABB::universe(),
+            };
+            (root, tree_nodes + 1)
+        };
+
+        // Every node but the root lives in its own Rc allocation, with two reference counts
+        let bytes = nodes * mem::size_of::<Self>() as u64
+            + (nodes - 1) * 2 * mem::size_of::<usize>() as u64;
+        stats::record_bvh_build(start.elapsed(), nodes, bytes);
+        node
     }
 
     #[allow(dead_code)]
     pub fn new(objects: &mut Vec<Rc<dyn Hittable>>, start: usize, end: usize) -> Self {
         // Build the bounding box of the span of source objects
         let mut bbox = AABB::empty();
-        for object in objects.iter() {
+        for object in objects[start..end].iter() {
             bbox = AABB::from_aabbs(bbox, object.bounding_box());
         }
 
@@ -39,7 +77,7 @@ impl BVHNode {
         } else if object_span == 2 {
             (objects[start].clone(), objects[start + 1].clone())
         } else {
-            objects.sort_by(|a, b| Self::box_compare(a, b, axis));
+            objects[start..end].sort_by(|a, b| Self::box_compare(a, b, axis));
             let mid = start + object_span / 2;
             (
                 Rc::new(Self::new(objects, start, mid)) as Rc<dyn Hittable>,
@@ -54,6 +92,17 @@ impl BVHNode {
         }
     }
 
+    fn node_count(object_span: usize) -> u64 {
+        // Number of nodes `new` builds over a span of objects: spans of one or two objects make
+        // a leaf node, longer ones are split in half
+        if object_span <= 2 {
+            1
+        } else {
+            let half = object_span / 2;
+            1 + Self::node_count(half) + Self::node_count(object_span - half)
+        }
+    }
+
     #[allow(dead_code)]
     fn box_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>, axis_index: usize) -> Ordering {
         let a_axis_interval = a.bounding_box()[axis_index];
@@ -68,6 +117,7 @@ impl BVHNode {
 
 impl Hittable for BVHNode {
     fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_bvh_node_test();
         if !self.bbox.hit(ray, ray_t) {
             return false;
         }
@@ -86,3 +136,40 @@ impl Hittable for BVHNode {
         self.bbox
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        material::Lambertian,
+        plane::Plane,
+        vec3::{Color, Point3, Vec3},
+    };
+
+    #[test]
+    fn tests_unbounded_objects_once() {
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let mut list = HittableList::default();
+        list.add(Rc::new(Plane::new(
+            Point3::default(),
+            Vec3::new(0.0, 1.0, 0.0),
+            mat.clone(),
+        )));
+        list.add(Rc::new(Plane::new(
+            Point3::new(0.0, 0.0, -5.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            mat,
+        )));
+        let bvh = BVHNode::from_list(list);
+
+        stats::reset_ray_counters();
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, -1.0), 0.0);
+        let mut rec = HitRecord::default();
+        assert!(bvh.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert_eq!(rec.t, 1.0);
+        let counts = stats::RenderStats::collect(Duration::ZERO, Duration::ZERO);
+        assert_eq!(counts.primitive_tests, 2);
+    }
+}
@@ -1,14 +1,142 @@
-use std::f64::INFINITY;
+use std::{
+    f64::INFINITY,
+    fs,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     exporter::Exporter,
+    film::{Aov, Film},
     hittable::{HitRecord, Hittable},
     interval::Interval,
+    progress::{CancelToken, Progress, ProgressObserver, TerminalProgress},
     ray::Ray,
-    utils::{degrees_to_radians, linear_to_gamma, random_double},
+    stats::{self, RenderStats},
+    utils::{degrees_to_radians, random_double},
     vec3::{Color, Point3, Vec3},
 };
 
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum PixelEstimator {
+    // Plain average of all samples
+    Mean,
+    // Samples are dealt round-robin into `buckets` partial averages and the pixel takes the
+    // median of those (by luminance), so a rare very bright sample only spoils one bucket.
+    MedianOfMeans { buckets: u32 },
+}
+
+impl PixelEstimator {
+    fn buckets(&self) -> usize {
+        match *self {
+            PixelEstimator::Mean => 1,
+            PixelEstimator::MedianOfMeans { buckets } => buckets.max(1) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct PathSample {
+    direct: Color,
+    indirect: Color,
+    // First-hit surface properties for the AOV layers
+    albedo: Color,
+    normal: Vec3,
+    // Individual emission events, only gathered when rendering light groups
+    emissions: Vec<Emission>,
+}
+
+#[derive(Debug, Clone)]
+struct Emission {
+    group: Option<Rc<str>>,
+    direct: bool,
+    radiance: Color,
+}
+
+impl PathSample {
+    fn aov(&self, aov: Aov) -> Color {
+        match aov {
+            Aov::Albedo => self.albedo,
+            Aov::Normal => (self.normal + 1.0) * 0.5,
+        }
+    }
+
+    fn add(&mut self, bounce: i32, radiance: Color) {
+        if bounce <= 1 {
+            self.direct += &radiance;
+        } else {
+            self.indirect += &radiance;
+        }
+    }
+
+    fn add_emission(&mut self, bounce: i32, radiance: Color, group: Option<Rc<str>>) {
+        if radiance.near_zero() {
+            return;
+        }
+        self.emissions.push(Emission {
+            group,
+            direct: bounce <= 1,
+            radiance,
+        });
+    }
+}
+
+// Film layers for each light group, created as the groups are first seen during the render
+#[derive(Default)]
+struct LightGroupLayers {
+    layers: Vec<(Option<Rc<str>>, usize)>,
+}
+
+impl LightGroupLayers {
+    fn layer(&mut self, film: &mut Film, group: &Option<Rc<str>>) -> usize {
+        if let Some((_, layer)) = self.layers.iter().find(|(name, _)| name == group) {
+            return *layer;
+        }
+        let name = format!("lightgroup.{}", group.as_deref().unwrap_or("default"));
+        let layer = film.add_layer(&name);
+        self.layers.push((group.clone(), layer));
+        layer
+    }
+}
+
+// Which of the image's tiles (numbered in scanline order) a render covers. Used to split one
+// image across several processes; the partial films are combined afterwards with `Film::merge`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileSelection {
+    All,
+    // Tiles `start..end`
+    Range { start: usize, end: usize },
+    // Every `count`th tile starting from `index`, which spreads expensive regions evenly
+    Interleaved { index: usize, count: usize },
+}
+
+impl TileSelection {
+    fn contains(&self, tile: usize) -> bool {
+        match *self {
+            TileSelection::All => true,
+            TileSelection::Range { start, end } => start <= tile && tile < end,
+            TileSelection::Interleaved { index, count } => tile % count.max(1) == index,
+        }
+    }
+}
+
+// Fixed-size block of the image, rendered as a unit
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Tile {
+    fn pixels(&self) -> u64 {
+        ((self.x1 - self.x0) * (self.y1 - self.y0)) as u64
+    }
+}
+
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i32,
@@ -24,7 +152,39 @@ pub struct Camera {
     pub defocus_angle: f64,
     pub focus_dist: f64,
 
-    pixel_samples_scale: f64,
+    pub print_stats: bool,
+    pub stats_path: Option<String>,
+    pub progress: Box<dyn ProgressObserver>,
+
+    // Progressive rendering: the image is refined in passes of `samples_per_pass` samples per
+    // pixel. With a time budget, passes keep being added until the budget runs out and
+    // `samples_per_pixel` is ignored; the pass running when it does stops at the next pixel.
+    // With a noise target, the render also stops once every pixel's relative error (see
+    // `Film::relative_error`) is below it, which needs the buckets of
+    // `PixelEstimator::MedianOfMeans`. Cancelling stops the render at the next pixel.
+    pub samples_per_pass: i32,
+    pub time_budget: Option<Duration>,
+    pub noise_target: Option<f64>,
+    pub cancel: CancelToken,
+
+    // Firefly suppression: each sample's direct and indirect radiance is clamped to these limits
+    // (INFINITY disables clamping), and the estimator decides how samples are combined.
+    pub clamp_direct: f64,
+    pub clamp_indirect: f64,
+    pub estimator: PixelEstimator,
+
+    // The film is filled tile by tile; `aovs` lists extra layers to record alongside the beauty.
+    // Tiles outside `tile_selection` are left empty (zero weight) in the film.
+    pub tile_size: i32,
+    pub tile_selection: TileSelection,
+    pub aovs: Vec<Aov>,
+
+    // When enabled, emission is also split into one film layer per light group (see
+    // `DiffuseLight::with_light_group`), named `lightgroup.<name>`. Untagged lights land in
+    // `lightgroup.default`, so the groups always add up to the beauty image.
+    pub light_groups: bool,
+    pub background_light_group: Option<Rc<str>>,
+
     image_height: i32,
     center: Point3,
     pixel00_loc: Point3,
@@ -35,7 +195,10 @@ pub struct Camera {
     w: Vec3,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
-    exporter: Box<dyn Exporter>,
+    exporters: Vec<Box<dyn Exporter>>,
+    film: Film,
+    export_time: Duration,
+    stats: RenderStats,
 }
 
 impl Camera {
@@ -52,7 +215,21 @@ impl Camera {
             vup: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
-            pixel_samples_scale: 0.0,
+            print_stats: true,
+            stats_path: None,
+            progress: Box::new(TerminalProgress::default()),
+            samples_per_pass: 1,
+            time_budget: None,
+            noise_target: None,
+            cancel: CancelToken::default(),
+            clamp_direct: f64::INFINITY,
+            clamp_indirect: f64::INFINITY,
+            estimator: PixelEstimator::Mean,
+            tile_size: 32,
+            tile_selection: TileSelection::All,
+            aovs: Vec::new(),
+            light_groups: false,
+            background_light_group: None,
             image_height: 0,
             center: Point3::default(),
             pixel00_loc: Point3::default(),
@@ -63,29 +240,148 @@ impl Camera {
             w: Vec3::default(),
             defocus_disk_u: Vec3::default(),
             defocus_disk_v: Vec3::default(),
-            // todo, don't unwrap
-            exporter: exporter,
+            exporters: vec![exporter],
+            film: Film::new(0, 0),
+            export_time: Duration::ZERO,
+            stats: RenderStats::default(),
         }
     }
 
+    #[allow(dead_code)]
+    pub fn add_exporter(&mut self, exporter: Box<dyn Exporter>) {
+        // Additional outputs, e.g. an AOV layer alongside the beauty image
+        self.exporters.push(exporter);
+    }
+
     pub fn render<T: Hittable>(&mut self, world: &T) {
         self.initialize();
+        let start = Instant::now();
+
+        // Samples are accumulated into the film over passes across the whole image, so the
+        // render can stop between (or part way through) passes and still produce a complete
+        // picture.
+        let mut film = Film::with_buckets(
+            self.image_width,
+            self.image_height,
+            self.estimator.buckets(),
+        );
+        let aov_layers: Vec<(Aov, usize)> = self
+            .aovs
+            .iter()
+            .map(|&aov| (aov, film.add_layer(aov.name())))
+            .collect();
+        let mut group_layers = LightGroupLayers::default();
+        let tiles = self.tiles();
+
+        let total_pixels = tiles.iter().map(Tile::pixels).sum::<u64>();
+        let mut progress = Progress {
+            total_pixels,
+            total_tiles: tiles.len() as u64,
+            total_samples: match self.time_budget {
+                Some(_) => 0,
+                None => total_pixels * self.samples_per_pixel as u64,
+            },
+            time_budget: self.time_budget,
+            ..Progress::default()
+        };
+        self.progress.on_start(&progress);
 
-        let _ = self.exporter.write_header();
+        if self.noise_target.is_some() && film.buckets() < 2 {
+            eprintln!("Ignoring the noise target: it needs PixelEstimator::MedianOfMeans");
+        }
 
-        for j in 0..self.image_height {
-            eprint!("Scanlines remaining: {}    \r", (self.image_height - j));
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::default();
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += &self.ray_color(&ray, self.max_depth, world);
+        // Past the time budget, the first pass finishes with a single sample per pixel so every
+        // pixel gets one, and later passes stop at the next pixel
+        let deadline = self.time_budget.map(|budget| start + budget);
+        let past_deadline = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        let mut samples_taken = 0;
+        let mut cancelled = false;
+        'passes: while let Some(pass_samples) = self.next_pass_samples(
+            progress.passes_done,
+            samples_taken,
+            start.elapsed(),
+            &film,
+            &tiles,
+        ) {
+            progress.completed_pixels = 0;
+            progress.completed_tiles = 0;
+            for tile in &tiles {
+                for j in tile.y0..tile.y1 {
+                    for i in tile.x0..tile.x1 {
+                        if cancelled
+                            || self.cancel.is_cancelled()
+                            || (progress.passes_done > 0 && past_deadline())
+                        {
+                            break 'passes;
+                        }
+
+                        for sample_index in 0..pass_samples {
+                            if sample_index > 0 && past_deadline() {
+                                break;
+                            }
+                            let ray = self.get_ray(i, j);
+                            let sample = self.ray_color(&ray, world);
+                            let direct_scale = Self::clamp_scale(sample.direct, self.clamp_direct);
+                            let indirect_scale =
+                                Self::clamp_scale(sample.indirect, self.clamp_indirect);
+                            let color =
+                                sample.direct * direct_scale + &(sample.indirect * indirect_scale);
+                            film.add_sample(i, j, color, 1.0);
+                            for &(aov, layer) in &aov_layers {
+                                film.add_layer_sample(layer, i, j, sample.aov(aov), 1.0);
+                            }
+                            for emission in &sample.emissions {
+                                let scale = if emission.direct {
+                                    direct_scale
+                                } else {
+                                    indirect_scale
+                                };
+                                let layer = group_layers.layer(&mut film, &emission.group);
+                                film.add_layer_sample(layer, i, j, emission.radiance * scale, 1.0);
+                            }
+                        }
+                    }
                 }
-                self.write_color(&(pixel_color * self.pixel_samples_scale));
+
+                progress.completed_tiles += 1;
+                progress.completed_pixels += tile.pixels();
+                progress.samples_done += tile.pixels() * pass_samples as u64;
+                progress.elapsed = start.elapsed();
+                cancelled = !self.progress.on_progress(&progress);
+                self.progress.on_film_update(&progress, &film);
+            }
+            samples_taken += pass_samples;
+            progress.passes_done += 1;
+        }
+
+        progress.elapsed = start.elapsed();
+        self.progress.on_film_update(&progress, &film);
+        self.progress.on_finish(&progress);
+
+        // Write out whatever has been accumulated, even if the render was cut short
+        self.film = film;
+        let export_start = Instant::now();
+        for exporter in self.exporters.iter_mut() {
+            if let Err(err) = exporter.export(&self.film) {
+                eprintln!("Failed to export render: {err}");
             }
         }
+        self.export_time = export_start.elapsed();
 
-        let _ = self.exporter.flush();
+        self.stats = RenderStats::collect(start.elapsed() - self.export_time, self.export_time);
+        self.report_stats();
+    }
+
+    #[allow(dead_code)]
+    pub fn film(&self) -> &Film {
+        // Film filled by the last call to `render`
+        &self.film
+    }
+
+    #[allow(dead_code)]
+    pub fn stats(&self) -> &RenderStats {
+        // Statistics gathered during the last call to `render`
+        &self.stats
     }
 
     fn initialize(&mut self) {
@@ -97,8 +393,6 @@ impl Camera {
             self.image_height
         };
 
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
         self.center = self.lookfrom;
 
         // Determine viewport dimensions
@@ -131,8 +425,98 @@ impl Camera {
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
 
-        // Setup the exporter class
-        self.exporter.set_dims(self.image_width, self.image_height);
+        // Start the statistics from a clean slate
+        stats::reset_ray_counters();
+        self.export_time = Duration::ZERO;
+    }
+
+    fn next_pass_samples(
+        &self,
+        passes_done: u32,
+        samples_taken: i32,
+        elapsed: Duration,
+        film: &Film,
+        tiles: &[Tile],
+    ) -> Option<i32> {
+        // Returns how many samples per pixel the next pass should take, or None once the render
+        // is finished.
+        let samples_per_pass = self.samples_per_pass.max(1);
+
+        if let Some(target) = self.noise_target
+            && Self::noise_below(film, tiles, target)
+        {
+            return None;
+        }
+
+        match self.time_budget {
+            Some(budget) => {
+                // Only start another pass if, going by the average so far, it should finish
+                // within the budget
+                let average_pass = if passes_done > 0 {
+                    elapsed / passes_done
+                } else {
+                    Duration::ZERO
+                };
+                (elapsed + average_pass <= budget).then_some(samples_per_pass)
+            }
+            None => {
+                let remaining = self.samples_per_pixel - samples_taken;
+                (remaining > 0).then(|| remaining.min(samples_per_pass))
+            }
+        }
+    }
+
+    fn noise_below(film: &Film, tiles: &[Tile], target: f64) -> bool {
+        // Whether every rendered pixel has a noise estimate, and all of them are under the target
+        tiles.iter().all(|tile| {
+            (tile.y0..tile.y1).all(|j| {
+                (tile.x0..tile.x1).all(|i| {
+                    film.relative_error(i, j)
+                        .is_some_and(|error| error <= target)
+                })
+            })
+        })
+    }
+
+    fn clamp_scale(color: Color, max: f64) -> f64 {
+        // Factor that scales the color down so no component exceeds `max`, preserving its hue
+        let peak = color.x().max(color.y()).max(color.z());
+        if peak > max { max / peak } else { 1.0 }
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        // Splits the image into tiles, in scanline order, keeping those in the selection
+        let size = self.tile_size.max(1);
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(size as usize) {
+            for x0 in (0..self.image_width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + size).min(self.image_width),
+                    y1: (y0 + size).min(self.image_height),
+                });
+            }
+        }
+        tiles
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| self.tile_selection.contains(*index))
+            .map(|(_, tile)| tile)
+            .collect()
+    }
+
+    fn report_stats(&self) {
+        if self.print_stats {
+            eprintln!();
+            eprintln!("{}", self.stats);
+        }
+
+        if let Some(path) = &self.stats_path
+            && let Err(err) = fs::write(path, self.stats.to_json())
+        {
+            eprintln!("Failed to write render statistics to {path}: {err}");
+        }
     }
 
     fn sample_square() -> Vec3 {
@@ -165,44 +549,61 @@ impl Camera {
         Ray::new(*ray_origin, ray_direction, ray_time)
     }
 
-    fn ray_color<T: Hittable>(&self, ray: &Ray, depth: i32, world: &T) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
+    fn ray_color<T: Hittable>(&self, ray: &Ray, world: &T) -> PathSample {
+        // Traces a path from the camera, splitting the light it gathers into direct (reaching the
+        // camera after at most one bounce) and indirect contributions.
+        let mut sample = PathSample::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+
+        for bounce in 0..self.max_depth {
+            if bounce == 0 {
+                stats::record_primary_ray();
+            } else {
+                stats::record_secondary_ray();
+            }
 
-        let mut rec = HitRecord::default();
+            let mut rec = HitRecord::default();
 
-        if !world.hit(ray, Interval::new(0.001, INFINITY), &mut rec) {
-            return self.background;
-        }
+            if !world.hit(&ray, Interval::new(0.001, INFINITY), &mut rec) {
+                let radiance = throughput * &self.background;
+                sample.add(bounce, radiance);
+                if self.light_groups {
+                    sample.add_emission(bounce, radiance, self.background_light_group.clone());
+                }
+                break;
+            }
+
+            let Some(mat) = &rec.mat else {
+                break;
+            };
 
-        if let Some(mat) = &rec.mat {
             let mut scattered = Ray::default();
             let mut attenuation = Color::default();
-            let color_from_emission = mat.emitted(rec.u, rec.v, rec.p);
+            let radiance = throughput * &mat.emitted(rec.u, rec.v, rec.p);
+            sample.add(bounce, radiance);
+            if self.light_groups {
+                sample.add_emission(bounce, radiance, mat.light_group());
+            }
 
-            if !mat.scatter(ray, &rec, &mut attenuation, &mut scattered) {
-                return color_from_emission;
+            let scatters = mat.scatter(&ray, &rec, &mut attenuation, &mut scattered);
+            if bounce == 0 {
+                sample.albedo = if scatters {
+                    attenuation
+                } else {
+                    Color::default()
+                };
+                sample.normal = rec.normal;
             }
 
-            let color_from_scatter = attenuation * &self.ray_color(&scattered, depth - 1, world);
+            if !scatters {
+                break;
+            }
 
-            return color_from_emission + &color_from_scatter;
+            throughput = throughput * &attenuation;
+            ray = scattered;
         }
 
-        return Color::default();
-    }
-
-    fn write_color(&mut self, color: &Color) {
-        let mut r = color.x();
-        let mut g = color.y();
-        let mut b = color.z();
-
-        // Apply a linear to gamma transform for gamma 2
-        r = linear_to_gamma(r);
-        g = linear_to_gamma(g);
-        b = linear_to_gamma(b);
-
-        let _ = self.exporter.write_pixel(Color::new(r, g, b));
+        sample
     }
 }
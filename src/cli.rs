@@ -0,0 +1,252 @@
+use std::{io, ops::Range, path::Path};
+
+use crate::{
+    bench::{self, BENCH_SCENES},
+    camera::TileSelection,
+    exporter,
+    film::Film,
+    scenes::{self, SCENE_NAMES},
+    utils::seed_rng,
+};
+
+const USAGE: &str = "\
+Usage:
+  rusttracer
+      Renders the default scene to render.bmp
+  rusttracer render [--scene NAME] [--output PATH] [--seed N]
+                    [--tiles START..END | --worker INDEX/COUNT] [--samples START..END]
+      Renders a scene, or part of it. Writing to a .film file keeps the raw float samples so
+      partial renders from several processes can be merged afterwards.
+  rusttracer merge --output PATH PARTIAL.film...
+      Combines partial renders of the same scene into one image (.bmp, .ppm or .film)
+  rusttracer bench [--label NAME] [--output results.csv] [--compare previous.csv]
+                   [--runs N] [--threshold PERCENT] [--scenes NAME,NAME...]
+      Renders the benchmark scenes and appends the results to a CSV file (bench.csv by default).
+      With --compare, results worse than the last ones recorded for each scene by more than the
+      threshold (5% by default) are reported and the command fails.";
+
+#[derive(Debug)]
+struct RenderArgs {
+    scene: String,
+    output: String,
+    seed: u64,
+    tiles: TileSelection,
+    samples: Option<Range<i32>>,
+}
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    match args.first().map(String::as_str) {
+        Some("render") => render(parse_render_args(&args[1..])?),
+        Some("merge") => merge(&args[1..]),
+        Some("bench") => run_bench(&args[1..]),
+        Some("help" | "--help" | "-h") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(usage_error("Unknown command")),
+    }
+}
+
+fn render(args: RenderArgs) -> io::Result<()> {
+    // Every process building the scene from the same seed gets the same world, even for scenes
+    // with random content
+    seed_rng(args.seed);
+    let exporter = exporter::from_path(&args.output)?;
+    let mut scene = scenes::by_name(&args.scene, exporter)
+        .ok_or_else(|| usage_error(&format!("Unknown scene {}", args.scene)))?;
+
+    scene.camera.tile_selection = args.tiles;
+    if let Some(samples) = &args.samples {
+        scene.camera.samples_per_pixel = samples.end - samples.start;
+    }
+
+    // Give each sample range its own random sequence, so processes splitting the samples of the
+    // same pixels don't all draw the same ones
+    let sample_start = args.samples.map_or(0, |samples| samples.start as u64);
+    seed_rng(args.seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ sample_start);
+
+    scene.render();
+    Ok(())
+}
+
+fn merge(args: &[String]) -> io::Result<()> {
+    let mut output = None;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(flag_value(arg, args.next())?),
+            _ => inputs.push(arg),
+        }
+    }
+    let output = output.ok_or_else(|| usage_error("merge needs an --output path"))?;
+    if inputs.is_empty() {
+        return Err(usage_error("merge needs at least one partial film"));
+    }
+
+    let mut film = Film::load(inputs[0])?;
+    for input in &inputs[1..] {
+        film.merge(&Film::load(input)?)
+            .map_err(|err| io::Error::new(err.kind(), format!("{input}: {err}")))?;
+    }
+
+    let mut exporter = exporter::from_path(output)?;
+    exporter.export(&film)
+}
+
+fn run_bench(args: &[String]) -> io::Result<()> {
+    let mut label = "unlabelled".to_string();
+    let mut output = "bench.csv";
+    let mut compare = None;
+    let mut runs = 1;
+    let mut threshold = 5.0;
+    let mut scene_names: Option<Vec<&str>> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        const FLAGS: [&str; 7] = [
+            "--label",
+            "--output",
+            "-o",
+            "--compare",
+            "--runs",
+            "--threshold",
+            "--scenes",
+        ];
+        if !FLAGS.contains(&arg.as_str()) {
+            return Err(usage_error(&format!("Unknown option {arg}")));
+        }
+        let value = flag_value(arg, args.next())?;
+        match arg.as_str() {
+            // Commas would break the CSV
+            "--label" => label = value.replace(',', "_"),
+            "--output" | "-o" => output = value,
+            "--compare" => compare = Some(value),
+            "--runs" => runs = parse_number(arg, value)?,
+            "--threshold" => threshold = parse_number(arg, value)?,
+            "--scenes" => scene_names = Some(value.split(',').collect()),
+            _ => unreachable!(),
+        }
+    }
+
+    let scenes: Vec<_> = match &scene_names {
+        None => BENCH_SCENES.iter().collect(),
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                BENCH_SCENES
+                    .iter()
+                    .find(|scene| scene.name == *name)
+                    .ok_or_else(|| usage_error(&format!("{name} isn't a benchmark scene")))
+            })
+            .collect::<io::Result<_>>()?,
+    };
+
+    // Read the previous results first, they may be in the file about to be appended to
+    let previous = compare
+        .map(|path| bench::read_results(Path::new(path)))
+        .transpose()?;
+
+    let mut results = Vec::new();
+    for scene in scenes {
+        eprintln!("Benchmarking {}...", scene.name);
+        results.push(bench::run_scene(scene, &label, runs));
+    }
+    print!("{}", bench::format_table(&results));
+    bench::append_results(Path::new(output), &results)?;
+
+    if let Some(previous) = previous {
+        let regressions = bench::regressions(&previous, &results, threshold / 100.0);
+        if !regressions.is_empty() {
+            for regression in &regressions {
+                println!("REGRESSION {regression}");
+            }
+            return Err(io::Error::other(format!(
+                "{} benchmark regression(s) over {threshold}%",
+                regressions.len()
+            )));
+        }
+        println!("No regressions over {threshold}%");
+    }
+    Ok(())
+}
+
+fn parse_render_args(args: &[String]) -> io::Result<RenderArgs> {
+    let mut parsed = RenderArgs {
+        scene: SCENE_NAMES[0].to_string(),
+        output: "render.bmp".to_string(),
+        seed: 0,
+        tiles: TileSelection::All,
+        samples: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        const FLAGS: [&str; 7] = [
+            "--scene",
+            "--output",
+            "-o",
+            "--seed",
+            "--tiles",
+            "--worker",
+            "--samples",
+        ];
+        if !FLAGS.contains(&arg.as_str()) {
+            return Err(usage_error(&format!("Unknown option {arg}")));
+        }
+        let value = flag_value(arg, args.next())?;
+        match arg.as_str() {
+            "--scene" => parsed.scene = value.to_string(),
+            "--output" | "-o" => parsed.output = value.to_string(),
+            "--seed" => parsed.seed = parse_number(arg, value)?,
+            "--tiles" => {
+                let range = parse_range(arg, value)?;
+                parsed.tiles = TileSelection::Range {
+                    start: range.start,
+                    end: range.end,
+                };
+            }
+            "--worker" => {
+                let (index, count) = value
+                    .split_once('/')
+                    .ok_or_else(|| usage_error("--worker expects INDEX/COUNT"))?;
+                let (index, count) = (parse_number(arg, index)?, parse_number(arg, count)?);
+                if index >= count {
+                    return Err(usage_error("--worker INDEX must be less than COUNT"));
+                }
+                parsed.tiles = TileSelection::Interleaved { index, count };
+            }
+            "--samples" => parsed.samples = Some(parse_range(arg, value)?),
+            _ => unreachable!(),
+        }
+    }
+    Ok(parsed)
+}
+
+fn flag_value<'a>(flag: &str, value: Option<&'a String>) -> io::Result<&'a str> {
+    value
+        .map(String::as_str)
+        .ok_or_else(|| usage_error(&format!("{flag} needs a value")))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| usage_error(&format!("Bad value {value} for {flag}")))
+}
+
+fn parse_range<T: std::str::FromStr + PartialOrd>(flag: &str, value: &str) -> io::Result<Range<T>> {
+    // START..END, end exclusive
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| usage_error(&format!("{flag} expects START..END")))?;
+    let range = parse_number(flag, start)?..parse_number(flag, end)?;
+    if range.start >= range.end {
+        return Err(usage_error(&format!("{flag} range {value} is empty")));
+    }
+    Ok(range)
+}
+
+fn usage_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{message}\n\n{USAGE}"))
+}
@@ -70,7 +70,9 @@ impl Hittable for ConstantMedium {
         rec.p = ray.at(rec.t);
 
         rec.normal = Vec3::new(1.0, 0.0, 0.0); // arbitary
+        rec.geometric_normal = rec.normal;
         rec.front_face = true; // also arbitary
+        rec.vertex_color = None;
         rec.mat = Some(self.phase_function.clone());
     
 
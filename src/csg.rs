@@ -0,0 +1,138 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+};
+
+// How a point's inside-ness in the two operands combines
+#[derive(Clone, Copy)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    fn combine(self, inside_a: bool, inside_b: bool) -> bool {
+        match self {
+            Operation::Union => inside_a || inside_b,
+            Operation::Intersection => inside_a && inside_b,
+            Operation::Difference => inside_a && !inside_b,
+        }
+    }
+}
+
+// Constructive solid geometry: the union, intersection or difference of two closed objects
+// (spheres, `box_new` boxes, capped cylinders, tori, other `Csg`s...), found by walking along the
+// ray through every surface of both operands in order and keeping track of whether the ray is
+// inside each. Crossings tell entries from exits by their `front_face`, so any closed hittable
+// works as an operand, including transformed ones.
+//
+// Surfaces keep their own materials, so a difference shows the cut-away object's material where
+// it carved into the first.
+pub struct Csg {
+    a: Rc<dyn Hittable>,
+    b: Rc<dyn Hittable>,
+    operation: Operation,
+    bbox: AABB,
+}
+
+// Relative distance to step past a surface before looking for the next one
+const STEP: f64 = 1e-9;
+
+impl Csg {
+    #[allow(dead_code)]
+    pub fn union(a: Rc<dyn Hittable>, b: Rc<dyn Hittable>) -> Self {
+        let bbox = AABB::from_aabbs(a.bounding_box(), b.bounding_box());
+        Self::new(a, b, Operation::Union, bbox)
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(a: Rc<dyn Hittable>, b: Rc<dyn Hittable>) -> Self {
+        // Only the overlap of the two boxes can be inside both
+        let (box_a, box_b) = (a.bounding_box(), b.bounding_box());
+        let overlap = |a: Interval, b: Interval| Interval::new(a.min.max(b.min), a.max.min(b.max));
+        let bbox = AABB::new(
+            overlap(box_a.x, box_b.x),
+            overlap(box_a.y, box_b.y),
+            overlap(box_a.z, box_b.z),
+        );
+        Self::new(a, b, Operation::Intersection, bbox)
+    }
+
+    #[allow(dead_code)]
+    pub fn difference(a: Rc<dyn Hittable>, b: Rc<dyn Hittable>) -> Self {
+        // `a` with `b` cut out of it
+        let bbox = a.bounding_box();
+        Self::new(a, b, Operation::Difference, bbox)
+    }
+
+    fn new(a: Rc<dyn Hittable>, b: Rc<dyn Hittable>, operation: Operation, bbox: AABB) -> Self {
+        Self {
+            a,
+            b,
+            operation,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Look past the end of the interval too: whether the first crossing of an operand is an
+        // entry or an exit is what tells if the ray starts inside it
+        let ahead = |t: f64| Interval::new(t, f64::INFINITY);
+
+        let mut rec_a = HitRecord::default();
+        let mut rec_b = HitRecord::default();
+        let mut hit_a = self.a.hit(ray, ahead(ray_t.min), &mut rec_a);
+        let mut hit_b = self.b.hit(ray, ahead(ray_t.min), &mut rec_b);
+        let mut inside_a = hit_a && !rec_a.front_face;
+        let mut inside_b = hit_b && !rec_b.front_face;
+        let inside = self.operation.combine(inside_a, inside_b);
+
+        loop {
+            // Take the nearer of the two next crossings
+            let crossing_a = match (hit_a, hit_b) {
+                (false, false) => return false,
+                (true, false) => true,
+                (false, true) => false,
+                (true, true) => rec_a.t <= rec_b.t,
+            };
+            let crossing = if crossing_a { &rec_a } else { &rec_b };
+            if crossing.t >= ray_t.max {
+                return false;
+            }
+
+            // The combined object's surface is wherever its inside-ness changes
+            if crossing_a {
+                inside_a = crossing.front_face;
+            } else {
+                inside_b = crossing.front_face;
+            }
+            let now_inside = self.operation.combine(inside_a, inside_b);
+            if now_inside != inside {
+                *rec = crossing.clone();
+                // The normal already faces the ray, only which side it's on can change, e.g. when
+                // leaving the cut-away object of a difference enters the result
+                rec.front_face = now_inside;
+                return true;
+            }
+
+            // Step past this crossing to the operand's next one
+            let next = ahead(crossing.t + STEP * crossing.t.abs().max(1.0));
+            if crossing_a {
+                hit_a = self.a.hit(ray, next, &mut rec_a);
+            } else {
+                hit_b = self.b.hit(ray, next, &mut rec_b);
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
@@ -0,0 +1,239 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::{
+    aabb::AABB,
+    disk::Disk,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// Cylinders, cones and frustums are one shape here: a surface of revolution around the axis from
+// `base` to `top`, whose radius changes linearly from the base radius to the top radius. The ends
+// are closed with flat caps unless made `uncapped`; a cone's tip has no cap.
+//
+// Texture coordinates on the side are u around the axis and v from base (0) to top (1). Like on a
+// `Disk`, u starts (u = 0) opposite the first vector of the axis' `Vec3::orthonormal_basis` and
+// turns towards the second. The caps are mapped like a `Disk` too, u around the axis and v from
+// the center (0) to the rim (1).
+pub struct Cylinder {
+    base: Point3,
+    // Local frame, `w` pointing along the axis
+    u_axis: Vec3,
+    v_axis: Vec3,
+    w_axis: Vec3,
+    height: f64,
+    base_radius: f64,
+    top_radius: f64,
+    capped: bool,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+}
+
+// Which part of the shape a hit is on
+enum Part {
+    Side,
+    BaseCap,
+    TopCap,
+}
+
+impl Cylinder {
+    #[allow(dead_code)]
+    pub fn new(base: Point3, top: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+        Self::frustum(base, top, radius, radius, mat)
+    }
+
+    #[allow(dead_code)]
+    pub fn cone(base: Point3, apex: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+        Self::frustum(base, apex, radius, 0.0, mat)
+    }
+
+    #[allow(dead_code)]
+    pub fn frustum(
+        base: Point3,
+        top: Point3,
+        base_radius: f64,
+        top_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        let axis = top - &base;
+        let height = axis.len();
+        assert!(height > 0.0, "Cylinder needs a base and top apart!");
+        let w_axis = axis / height;
+        let (u_axis, v_axis) = w_axis.orthonormal_basis();
+        let base_radius = base_radius.max(0.0);
+        let top_radius = top_radius.max(0.0);
+        Self {
+            base,
+            u_axis,
+            v_axis,
+            w_axis,
+            height,
+            base_radius,
+            top_radius,
+            capped: true,
+            mat,
+            bbox: AABB::from_aabbs(
+                Disk::bounds(base, w_axis, base_radius),
+                Disk::bounds(top, w_axis, top_radius),
+            ),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn uncapped(mut self) -> Self {
+        // An open tube, visible from the inside too
+        self.capped = false;
+        self
+    }
+
+    fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            v.dot(&self.u_axis),
+            v.dot(&self.v_axis),
+            v.dot(&self.w_axis),
+        )
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        self.u_axis * v.x() + &(self.v_axis * v.y()) + &(self.w_axis * v.z())
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        // Work in the local frame, with the base at the origin and the axis along z
+        let origin = self.to_local(&(ray.origin - &self.base));
+        let dir = self.to_local(&ray.dir);
+        let (ox, oy, oz) = (origin.x(), origin.y(), origin.z());
+        let (dx, dy, dz) = (dir.x(), dir.y(), dir.z());
+
+        // The radius at height z is r0 + k z
+        let r0 = self.base_radius;
+        let k = (self.top_radius - self.base_radius) / self.height;
+
+        let mut closest = ray_t.max;
+        let mut part = None;
+
+        // Side: x^2 + y^2 = (r0 + k z)^2, for z between the ends
+        let radius_at_origin = r0 + k * oz;
+        let a = dx * dx + dy * dy - k * k * dz * dz;
+        let half_b = ox * dx + oy * dy - k * dz * radius_at_origin;
+        let c = ox * ox + oy * oy - radius_at_origin * radius_at_origin;
+        let mut roots = [f64::NAN; 2];
+        if a.abs() > 1e-12 {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                roots = [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a];
+            }
+        } else if half_b.abs() > 1e-12 {
+            // Ray parallel to a cone's side, it crosses the surface once
+            roots[0] = -c / (2.0 * half_b);
+        }
+        for t in roots {
+            let z = oz + t * dz;
+            if ray_t.surrounds(t) && t < closest && (0.0..=self.height).contains(&z) {
+                closest = t;
+                part = Some(Part::Side);
+            }
+        }
+
+        // Caps: the planes at either end, within that end's radius
+        if self.capped && dz.abs() > 1e-12 {
+            for (z, radius, cap) in [
+                (0.0, self.base_radius, Part::BaseCap),
+                (self.height, self.top_radius, Part::TopCap),
+            ] {
+                let t = (z - oz) / dz;
+                let (x, y) = (ox + t * dx, oy + t * dy);
+                if radius > 0.0
+                    && ray_t.surrounds(t)
+                    && t < closest
+                    && x * x + y * y <= radius * radius
+                {
+                    closest = t;
+                    part = Some(cap);
+                }
+            }
+        }
+
+        let Some(part) = part else {
+            return false;
+        };
+
+        let t = closest;
+        let (x, y, z) = (ox + t * dx, oy + t * dy, oz + t * dz);
+        let angle = y.atan2(x);
+        rec.u = (angle + PI) / (2.0 * PI);
+        let local_normal = match part {
+            Part::Side => {
+                // The side tilts inwards as the radius shrinks. Exactly at a cone's apex there's
+                // no slope left to go by, so the normal points out of the tip along the axis.
+                rec.v = z / self.height;
+                let normal = Vec3::new(x, y, -k * (r0 + k * z));
+                if normal.near_zero() {
+                    Vec3::new(0.0, 0.0, -k.signum())
+                } else {
+                    normal.unit_vector()
+                }
+            }
+            Part::BaseCap => {
+                rec.v = (x * x + y * y).sqrt() / self.base_radius;
+                Vec3::new(0.0, 0.0, -1.0)
+            }
+            Part::TopCap => {
+                rec.v = (x * x + y * y).sqrt() / self.top_radius;
+                Vec3::new(0.0, 0.0, 1.0)
+            }
+        };
+
+        rec.t = t;
+        rec.p = ray.at(t);
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(ray, self.to_world(&local_normal));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, vec3::Color};
+
+    #[test]
+    fn apex_hits_get_the_axis_as_normal() {
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let up = Point3::new(0.0, 1.0, 0.0);
+        // Apex on top, then a cone standing on its apex
+        for (cone, origin, normal) in [
+            (
+                Cylinder::cone(Point3::default(), up, 1.0, mat.clone()),
+                up * 2.0,
+                up,
+            ),
+            (
+                Cylinder::frustum(Point3::default(), up, 0.0, 1.0, mat),
+                up * -1.0,
+                up * -1.0,
+            ),
+        ] {
+            let ray = Ray::new(origin, normal * -1.0, 0.0);
+            let mut rec = HitRecord::default();
+            assert!(cone.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!((rec.t - 1.0).abs() < 1e-9);
+            assert!((rec.normal - &normal).near_zero(), "{:?}", rec.normal);
+            assert!(rec.front_face);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::{
+    aabb::AABB,
+    hittable::HitRecord,
+    material::Material,
+    planar::{Planar, PlanarShape, ellipse_bounds},
+    vec3::{Point3, Vec3},
+};
+
+// Flat disk facing along `normal`, or an annulus (a disk with a hole) when the inner radius is
+// above zero. Texture coordinates are polar: u is the angle around the normal, starting (u = 0)
+// opposite the first basis vector `Vec3::orthonormal_basis` gives and turning towards the
+// second, and v runs from the inner to the outer edge.
+pub type Disk = Planar<Annulus>;
+
+// Ring between the ellipse with semi-axes `u` and `v` around `q` and the same ellipse scaled
+// down by `inner` (from 0, a full disk, to 1)
+pub struct Annulus {
+    inner: f64,
+}
+
+impl Disk {
+    #[allow(dead_code)]
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mat: Rc<dyn Material>) -> Self {
+        Self::annulus(center, normal, 0.0, radius, mat)
+    }
+
+    #[allow(dead_code)]
+    pub fn annulus(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        let (u_axis, v_axis) = normal.unit_vector().orthonormal_basis();
+        let outer_radius = outer_radius.max(0.0);
+        let inner = if outer_radius > 0.0 {
+            inner_radius.clamp(0.0, outer_radius) / outer_radius
+        } else {
+            0.0
+        };
+        Self::from_shape(
+            Annulus { inner },
+            center,
+            u_axis * outer_radius,
+            v_axis * outer_radius,
+            mat,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn bounds(center: Point3, normal: Vec3, radius: f64) -> AABB {
+        // Bounding box of a circle: along each axis it reaches out by the radius times the sine
+        // of the angle between that axis and the (unit) normal
+        let extent = Vec3::new(
+            radius * (1.0 - normal.x() * normal.x()).max(0.0).sqrt(),
+            radius * (1.0 - normal.y() * normal.y()).max(0.0).sqrt(),
+            radius * (1.0 - normal.z() * normal.z()).max(0.0).sqrt(),
+        );
+        AABB::from_points(center - &extent, center + &extent)
+    }
+}
+
+impl PlanarShape for Annulus {
+    fn is_interior(&self, alpha: f64, beta: f64, rec: &mut HitRecord) -> bool {
+        let radius = (alpha * alpha + beta * beta).sqrt();
+        if radius < self.inner || radius > 1.0 {
+            return false;
+        }
+
+        rec.u = (beta.atan2(alpha) + PI) / (2.0 * PI);
+        rec.v = if self.inner < 1.0 {
+            (radius - self.inner) / (1.0 - self.inner)
+        } else {
+            0.0
+        };
+        true
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> AABB {
+        ellipse_bounds(q, u, v)
+    }
+}
@@ -1,114 +1,145 @@
 use std::{
     fs::File,
     io::{self, BufWriter, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use byteorder::{LittleEndian, WriteBytesExt};
 
-use crate::{interval::Interval, vec3::Color};
+use crate::{film::Film, interval::Interval, utils::linear_to_gamma, vec3::Color};
 
 pub trait Exporter {
-    fn set_dims(&mut self, width: i32, height: i32);
-    fn write_header(&mut self) -> io::Result<()>;
-    fn write_pixel(&mut self, color: Color) -> io::Result<()>;
-    fn flush(&mut self) -> io::Result<()>;
+    fn export(&mut self, film: &Film) -> io::Result<()>;
+}
+
+fn film_layer(film: &Film, layer: &Option<String>) -> io::Result<Option<usize>> {
+    // Looks up the layer an exporter was asked to write, None meaning the beauty image
+    match layer {
+        None => Ok(None),
+        Some(name) => film.layer_index(name).map(Some).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Film has no layer named {name}"),
+            )
+        }),
+    }
+}
+
+fn pixel_bytes(film: &Film, layer: Option<usize>, x: i32, y: i32) -> [u8; 3] {
+    let color = match layer {
+        None => film.pixel(x, y),
+        Some(layer) => film.layer_pixel(layer, x, y),
+    };
+    color_to_bytes(color)
+}
+
+// The one tone mapping from linear film values to 8-bit pixels, shared by everything that shows
+// or writes an image so they all agree
+pub(crate) fn color_to_bytes(color: Color) -> [u8; 3] {
+    // Apply a linear to gamma transform for gamma 2, then translate the [0,1] component values
+    // to the byte range [0,255]
+    let intensity = Interval::new(0.0, 0.999);
+    [
+        (255.999 * intensity.clamp(linear_to_gamma(color.x()))) as u8,
+        (255.999 * intensity.clamp(linear_to_gamma(color.y()))) as u8,
+        (255.999 * intensity.clamp(linear_to_gamma(color.z()))) as u8,
+    ]
 }
 
 #[derive(Debug)]
 pub struct PPMExporter {
-    file: BufWriter<File>,
-    width: i32,
-    height: i32,
+    path: PathBuf,
+    layer: Option<String>,
 }
 
 impl PPMExporter {
     #[allow(dead_code)]
     pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = File::create(path)?;
+        // Create the file up front so a bad path is reported before rendering
+        File::create(&path)?;
         Ok(Self {
-            file: BufWriter::new(file),
-            width: 0,
-            height: 0,
+            path: path.as_ref().to_path_buf(),
+            layer: None,
         })
     }
-}
 
-impl Exporter for PPMExporter {
-    fn set_dims(&mut self, width: i32, height: i32) {
-        self.width = width;
-        self.height = height;
+    #[allow(dead_code)]
+    pub fn with_layer(mut self, layer: &str) -> Self {
+        // Write the named film layer instead of the beauty image
+        self.layer = Some(layer.to_string());
+        self
     }
+}
 
-    fn write_header(&mut self) -> io::Result<()> {
-        let file = &mut self.file;
+impl Exporter for PPMExporter {
+    fn export(&mut self, film: &Film) -> io::Result<()> {
+        let layer = film_layer(film, &self.layer)?;
+        let mut file = BufWriter::new(File::create(&self.path)?);
 
         // Header
-        file.write_fmt(format_args!("P3\n{} {}\n255\n", self.width, self.height))?;
-
-        Ok(())
-    }
-
-    fn write_pixel(&mut self, color: Color) -> io::Result<()> {
-        let file = &mut self.file;
-
-        // Translate the [0,1] component values to the byte range [0,255]
-        let intensity = Interval::new(0.0, 0.999);
-        let ir = (255.999 * intensity.clamp(color.x())) as i64;
-        let ig = (255.999 * intensity.clamp(color.y())) as i64;
-        let ib = (255.999 * intensity.clamp(color.z())) as i64;
-
-        // Write out the pixel color components
-        file.write_fmt(format_args!("{ir} {ig} {ib}\n"))?;
-
-        Ok(())
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        self.file.flush()?;
-        Ok(())
+        file.write_fmt(format_args!(
+            "P3\n{} {}\n255\n",
+            film.width(),
+            film.height()
+        ))?;
+
+        // Pixels, top to bottom
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                let [r, g, b] = pixel_bytes(film, layer, x, y);
+                file.write_fmt(format_args!("{r} {g} {b}\n"))?;
+            }
+        }
+
+        file.flush()
     }
 }
 
 #[derive(Debug)]
 pub struct BMPExporter {
-    file: BufWriter<File>,
-    width: i32,
-    height: i32,
+    path: PathBuf,
+    layer: Option<String>,
 }
 
 impl BMPExporter {
     #[allow(dead_code)]
     pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = File::create(path)?;
+        // Create the file up front so a bad path is reported before rendering
+        File::create(&path)?;
         Ok(Self {
-            file: BufWriter::new(file),
-            width: 0,
-            height: 0,
+            path: path.as_ref().to_path_buf(),
+            layer: None,
         })
     }
+
+    #[allow(dead_code)]
+    pub fn with_layer(mut self, layer: &str) -> Self {
+        // Write the named film layer instead of the beauty image
+        self.layer = Some(layer.to_string());
+        self
+    }
 }
 
 impl Exporter for BMPExporter {
-    fn set_dims(&mut self, width: i32, height: i32) {
-        self.width = width;
-        // Note : negative height because BMPs are usually bottom up data structures and we render top down
-        self.height = -height;
-    }
+    fn export(&mut self, film: &Film) -> io::Result<()> {
+        let layer = film_layer(film, &self.layer)?;
+        let mut file = BufWriter::new(File::create(&self.path)?);
 
-    fn write_header(&mut self) -> io::Result<()> {
-        let file = &mut self.file;
+        // Rows are padded out to a multiple of four bytes
+        let row_padding = (4 - (film.width() * 3) % 4) % 4;
+        let image_size = (film.width() * 3 + row_padding) * film.height();
 
         // Header
         file.write_all(&[66, 77])?; // signature
-        file.write_u32::<LittleEndian>(0x38)?; // file size
+        file.write_u32::<LittleEndian>(0x36 + image_size as u32)?; // file size
         file.write_u32::<LittleEndian>(0x00)?; // reserved
         file.write_u32::<LittleEndian>(0x36)?; // data offset
 
         // Info Header
         file.write_u32::<LittleEndian>(0x28)?; // size of info header
-        file.write_i32::<LittleEndian>(self.width)?; // width
-        file.write_i32::<LittleEndian>(self.height)?; // height
+        file.write_i32::<LittleEndian>(film.width())?; // width
+        // Note : negative height because BMPs are usually bottom up data structures and we render top down
+        file.write_i32::<LittleEndian>(-film.height())?; // height
         file.write_u16::<LittleEndian>(1)?; // num planes
         file.write_u16::<LittleEndian>(24)?; // bits per pixel
         file.write_u32::<LittleEndian>(0)?; // compression // none
@@ -118,28 +149,61 @@ impl Exporter for BMPExporter {
         file.write_u32::<LittleEndian>(0)?; // number of colors used
         file.write_u32::<LittleEndian>(0)?; // important colors
 
-        Ok(())
-    }
+        // Pixels, top to bottom
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                let [r, g, b] = pixel_bytes(film, layer, x, y);
+                file.write_all(&[b, g, r])?;
+            }
+            file.write_all(&[0; 3][..row_padding as usize])?;
+        }
 
-    fn write_pixel(&mut self, color: Color) -> io::Result<()> {
-        let file = &mut self.file;
+        file.flush()
+    }
+}
 
-        // Translate the [0,1] component values to the byte range [0,255]
-        let intensity = Interval::new(0.0, 0.999);
-        let r = (255.999 * intensity.clamp(color.x())) as u8;
-        let g = (255.999 * intensity.clamp(color.y())) as u8;
-        let b = (255.999 * intensity.clamp(color.z())) as u8;
+// Writes the raw float film rather than an image, e.g. for a partial render that will be
+// merged with others later (see `Film::save` / `Film::load`)
+#[derive(Debug)]
+pub struct FilmExporter {
+    path: PathBuf,
+}
 
-        // Write out the pixel color components
-        file.write_u8(b)?;
-        file.write_u8(g)?;
-        file.write_u8(r)?;
+impl FilmExporter {
+    #[allow(dead_code)]
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        // Create the file up front so a bad path is reported before rendering
+        File::create(&path)?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+}
 
-        Ok(())
+impl Exporter for FilmExporter {
+    fn export(&mut self, film: &Film) -> io::Result<()> {
+        film.save(&self.path)
     }
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.file.flush()?;
-        Ok(())
+#[allow(dead_code)]
+pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Exporter>> {
+    // Picks an exporter from the file extension: .bmp, .ppm or .film
+    let extension = path
+        .as_ref()
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("bmp") => Ok(Box::new(BMPExporter::new(path)?)),
+        Some("ppm") => Ok(Box::new(PPMExporter::new(path)?)),
+        Some("film") => Ok(Box::new(FilmExporter::new(path)?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Unknown output format for {}, expected .bmp, .ppm or .film",
+                path.as_ref().display()
+            ),
+        )),
     }
 }
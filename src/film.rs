@@ -0,0 +1,410 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::vec3::Color;
+
+// Identifies (and versions) the raw film files written by `Film::save`
+const FILM_MAGIC: &[u8; 8] = b"RTFILM02";
+// Luminance below which `relative_error` measures noise against this instead
+const NOISE_FLOOR: f64 = 0.01;
+
+// Arbitrary output variables the camera can record alongside the beauty image
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Albedo, // surface color at the first hit
+    Normal, // shading normal at the first hit, remapped from [-1,1] to [0,1]
+}
+
+impl Aov {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    sums: Vec<Color>,
+}
+
+// Linear float framebuffer. Samples can be added to any pixel in any order; each pixel keeps a
+// weighted sum of radiance, split across `buckets` partial sums so outlier-rejecting estimators
+// can be resolved at the end. Extra named layers (AOVs, light groups, ...) are accumulated into
+// the same buckets with the same weights as the beauty image, and resolved from the buckets the
+// beauty picked, so light groups still add up to the beauty.
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: i32,
+    height: i32,
+    buckets: usize,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    sample_counts: Vec<u32>,
+    layers: Vec<Layer>,
+}
+
+impl Film {
+    #[allow(dead_code)]
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_buckets(width, height, 1)
+    }
+
+    pub fn with_buckets(width: i32, height: i32, buckets: usize) -> Self {
+        let pixels = (width * height) as usize;
+        let buckets = buckets.max(1);
+        Self {
+            width,
+            height,
+            buckets,
+            sums: vec![Color::default(); pixels * buckets],
+            weights: vec![0.0; pixels * buckets],
+            sample_counts: vec![0; pixels],
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    #[allow(dead_code)]
+    pub fn buckets(&self) -> usize {
+        self.buckets
+    }
+
+    pub fn add_layer(&mut self, name: &str) -> usize {
+        // Adds a named layer (or finds the existing one) and returns its index
+        if let Some(index) = self.layer_index(name) {
+            return index;
+        }
+        self.layers.push(Layer {
+            name: name.to_string(),
+            sums: vec![Color::default(); self.sums.len()],
+        });
+        self.layers.len() - 1
+    }
+
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    #[allow(dead_code)]
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn add_sample(&mut self, x: i32, y: i32, color: Color, weight: f64) {
+        // Samples are dealt round-robin into the pixel's buckets
+        let pixel = self.index(x, y);
+        let bucket = pixel * self.buckets + self.sample_counts[pixel] as usize % self.buckets;
+        self.sums[bucket] += &(color * weight);
+        self.weights[bucket] += weight;
+        self.sample_counts[pixel] += 1;
+    }
+
+    pub fn add_layer_sample(&mut self, layer: usize, x: i32, y: i32, value: Color, weight: f64) {
+        // Layers share the beauty weights and buckets, so this should follow the `add_sample`
+        // call for the same pixel and weight, landing in the bucket that sample went to.
+        let pixel = self.index(x, y);
+        let last_sample = self.sample_counts[pixel] as usize + self.buckets - 1;
+        let bucket = pixel * self.buckets + last_sample % self.buckets;
+        self.layers[layer].sums[bucket] += &(value * weight);
+    }
+
+    #[allow(dead_code)]
+    pub fn weight(&self, x: i32, y: i32) -> f64 {
+        let first = self.index(x, y) * self.buckets;
+        self.weights[first..first + self.buckets].iter().sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn sample_count(&self, x: i32, y: i32) -> u32 {
+        self.sample_counts[self.index(x, y)]
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Color {
+        // Resolved linear color of the pixel. With a single bucket this is the weighted mean;
+        // otherwise it's the median (by luminance) of the bucket means.
+        self.resolve(&self.sums, self.index(x, y))
+    }
+
+    fn resolve(&self, sums: &[Color], pixel: usize) -> Color {
+        // Averages the means of the median buckets, the middle one or two by beauty luminance
+        let first = pixel * self.buckets;
+        let mut buckets: Vec<usize> = (first..first + self.buckets)
+            .filter(|&bucket| self.weights[bucket] > 0.0)
+            .collect();
+        let luminance = |bucket: usize| (self.sums[bucket] / self.weights[bucket]).luminance();
+        buckets.sort_by(|&a, &b| luminance(a).total_cmp(&luminance(b)));
+
+        let n = buckets.len();
+        let median = match n {
+            0 => return Color::default(),
+            _ if n % 2 == 1 => &buckets[n / 2..n / 2 + 1],
+            _ => &buckets[n / 2 - 1..n / 2 + 1],
+        };
+        let mut color = Color::default();
+        for &bucket in median {
+            color += &(sums[bucket] / self.weights[bucket]);
+        }
+        color / median.len() as f64
+    }
+
+    #[allow(dead_code)]
+    pub fn relative_error(&self, x: i32, y: i32) -> Option<f64> {
+        // Estimated standard error of the pixel's luminance, relative to the luminance itself,
+        // from the spread of its bucket means. None until at least two buckets have samples.
+        let first = self.index(x, y) * self.buckets;
+        let means: Vec<f64> = (first..first + self.buckets)
+            .filter(|&bucket| self.weights[bucket] > 0.0)
+            .map(|bucket| (self.sums[bucket] / self.weights[bucket]).luminance())
+            .collect();
+        if means.len() < 2 {
+            return None;
+        }
+
+        let n = means.len() as f64;
+        let mean = means.iter().sum::<f64>() / n;
+        let variance = means.iter().map(|m| (m - mean) * (m - mean)).sum::<f64>() / (n - 1.0);
+        // Dark pixels are judged against a floor, or they'd never be quiet enough
+        Some((variance / n).sqrt() / mean.max(NOISE_FLOOR))
+    }
+
+    #[allow(dead_code)]
+    pub fn layer_pixel(&self, layer: usize, x: i32, y: i32) -> Color {
+        self.resolve(&self.layers[layer].sums, self.index(x, y))
+    }
+
+    #[allow(dead_code)]
+    pub fn relight(&self, weights: &[(&str, f64)]) -> Film {
+        // Rebuilds the image from its light group layers, scaling each group by the given
+        // weight (groups not listed keep a weight of one). The film must have been rendered with
+        // `Camera::light_groups` enabled. Group layers are scaled along with the image, and the
+        // other layers are kept as they are.
+        let mut film = self.clone();
+        let groups: Vec<(usize, f64)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, layer)| {
+                let name = layer.name.strip_prefix("lightgroup.")?;
+                let weight = weights
+                    .iter()
+                    .find(|(group, _)| *group == name)
+                    .map_or(1.0, |(_, weight)| *weight);
+                Some((index, weight))
+            })
+            .collect();
+
+        film.sums.fill(Color::default());
+        for (layer, group_weight) in groups {
+            let layer = &mut film.layers[layer];
+            for (sum, group_sum) in film.sums.iter_mut().zip(layer.sums.iter_mut()) {
+                *group_sum *= group_weight;
+                *sum += group_sum;
+            }
+        }
+        film
+    }
+
+    #[allow(dead_code)]
+    pub fn merge(&mut self, other: &Film) -> io::Result<()> {
+        // Adds another film's samples to this one. Pixel sums and weights simply add up, so the
+        // merged pixels are the sample weighted averages of both films; layers are matched by
+        // name. Used to combine partial renders of the same image (different tiles or different
+        // samples of the same pixels).
+        if (other.width, other.height, other.buckets) != (self.width, self.height, self.buckets) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Cannot merge a {}x{} film with {} buckets into a {}x{} film with {} buckets",
+                    other.width, other.height, other.buckets, self.width, self.height, self.buckets
+                ),
+            ));
+        }
+
+        for (sum, other_sum) in self.sums.iter_mut().zip(&other.sums) {
+            *sum += other_sum;
+        }
+        for (weight, other_weight) in self.weights.iter_mut().zip(&other.weights) {
+            *weight += other_weight;
+        }
+        for (count, other_count) in self.sample_counts.iter_mut().zip(&other.sample_counts) {
+            *count += other_count;
+        }
+        for other_layer in &other.layers {
+            let layer = self.add_layer(&other_layer.name);
+            for (sum, other_sum) in self.layers[layer].sums.iter_mut().zip(&other_layer.sums) {
+                *sum += other_sum;
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        // Writes the raw accumulated film (not the resolved image), so it can be loaded back
+        // and merged with other partial renders
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(FILM_MAGIC)?;
+        file.write_i32::<LittleEndian>(self.width)?;
+        file.write_i32::<LittleEndian>(self.height)?;
+        file.write_u32::<LittleEndian>(self.buckets as u32)?;
+        file.write_u32::<LittleEndian>(self.layers.len() as u32)?;
+
+        for (sum, weight) in self.sums.iter().zip(&self.weights) {
+            write_color(&mut file, sum)?;
+            file.write_f64::<LittleEndian>(*weight)?;
+        }
+        for count in &self.sample_counts {
+            file.write_u32::<LittleEndian>(*count)?;
+        }
+        for layer in &self.layers {
+            file.write_u32::<LittleEndian>(layer.name.len() as u32)?;
+            file.write_all(layer.name.as_bytes())?;
+            for sum in &layer.sums {
+                write_color(&mut file, sum)?;
+            }
+        }
+
+        file.flush()
+    }
+
+    #[allow(dead_code)]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Film> {
+        // Reads a film written by `save`
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut file = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != FILM_MAGIC {
+            return Err(invalid("Not a film file"));
+        }
+        let width = file.read_i32::<LittleEndian>()?;
+        let height = file.read_i32::<LittleEndian>()?;
+        let buckets = file.read_u32::<LittleEndian>()? as usize;
+        let layer_count = file.read_u32::<LittleEndian>()?;
+        if width < 0 || height < 0 || buckets == 0 {
+            return Err(invalid("Bad film dimensions"));
+        }
+
+        // Check the header against the file's size before allocating anything
+        let header_len = FILM_MAGIC.len() as u64 + 4 * 4;
+        let expected = expected_file_len(width, height, buckets, layer_count);
+        if expected.is_none_or(|expected| expected > file_len.saturating_sub(header_len)) {
+            return Err(invalid("Film file is truncated or its header is corrupt"));
+        }
+
+        let mut film = Film::with_buckets(width, height, buckets);
+        for bucket in 0..film.sums.len() {
+            film.sums[bucket] = read_color(&mut file)?;
+            film.weights[bucket] = file.read_f64::<LittleEndian>()?;
+        }
+        for count in film.sample_counts.iter_mut() {
+            *count = file.read_u32::<LittleEndian>()?;
+        }
+        for _ in 0..layer_count {
+            let mut name = vec![0; file.read_u32::<LittleEndian>()? as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid("Bad film layer name"))?;
+            let layer = film.add_layer(&name);
+            for sum in film.layers[layer].sums.iter_mut() {
+                *sum = read_color(&mut file)?;
+            }
+        }
+        Ok(film)
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        assert!(
+            0 <= x && x < self.width && 0 <= y && y < self.height,
+            "Pixel out of bounds!"
+        );
+        (y * self.width + x) as usize
+    }
+}
+
+fn expected_file_len(width: i32, height: i32, buckets: usize, layer_count: u32) -> Option<u64> {
+    // Smallest size of a film's data after the header: each bucket is a color and a weight, each
+    // pixel has a sample count, and each layer a name (of at least its length) and a color per
+    // bucket. None if that overflows.
+    let color_bytes = 3 * 8;
+    let pixels = (width as u64).checked_mul(height as u64)?;
+    let samples = pixels.checked_mul(buckets as u64)?;
+    let beauty = samples.checked_mul(color_bytes + 8)?;
+    let counts = pixels.checked_mul(4)?;
+    let layer = samples.checked_mul(color_bytes)?.checked_add(4)?;
+    let layers = layer.checked_mul(layer_count as u64)?;
+    beauty.checked_add(counts)?.checked_add(layers)
+}
+
+fn write_color<W: Write>(writer: &mut W, color: &Color) -> io::Result<()> {
+    writer.write_f64::<LittleEndian>(color.x())?;
+    writer.write_f64::<LittleEndian>(color.y())?;
+    writer.write_f64::<LittleEndian>(color.z())
+}
+
+fn read_color<R: Read>(reader: &mut R) -> io::Result<Color> {
+    let x = reader.read_f64::<LittleEndian>()?;
+    let y = reader.read_f64::<LittleEndian>()?;
+    let z = reader.read_f64::<LittleEndian>()?;
+    Ok(Color::new(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = env::temp_dir().join("rusttracer_film_round_trip.film");
+        let mut film = Film::with_buckets(3, 2, 2);
+        let layer = film.add_layer("albedo");
+        film.add_sample(1, 1, Color::new(1.0, 2.0, 3.0), 1.0);
+        film.add_layer_sample(layer, 1, 1, Color::new(0.5, 0.5, 0.5), 1.0);
+        film.save(&path).unwrap();
+
+        let loaded = Film::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            (loaded.width(), loaded.height(), loaded.buckets()),
+            (3, 2, 2)
+        );
+        assert_eq!(loaded.pixel(1, 1).y(), 2.0);
+        assert_eq!(loaded.layer_pixel(layer, 1, 1).x(), 0.5);
+    }
+
+    #[test]
+    fn load_rejects_a_header_larger_than_the_file() {
+        // A corrupt header claiming a huge film must fail before allocating it
+        let path = env::temp_dir().join("rusttracer_film_corrupt.film");
+        let mut bytes = FILM_MAGIC.to_vec();
+        for value in [i32::MAX, i32::MAX, 1000, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        fs::write(&path, &bytes).unwrap();
+
+        let err = Film::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
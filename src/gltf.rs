@@ -0,0 +1,959 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+use nalgebra::{Matrix3, Matrix4, Quaternion, UnitQuaternion, Vector3, Vector4};
+
+use crate::{
+    camera::Camera,
+    hittable::HittableList,
+    json::Json,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::TriangleMesh,
+    texture::{ImageTexture, SolidColor, Texture},
+    vec3::{Color, Point3, Vec3},
+};
+
+// glTF 2.0 importer, for both `.gltf` (JSON, with external or base64 embedded buffers) and `.glb`
+// (binary) files. The default scene's node hierarchy is flattened: every mesh primitive becomes
+// a `TriangleMesh` with its vertices moved into world space, keeping normals, the first UV set
+// and vertex colors. Perspective cameras are collected with their world placement and can be
+// applied to a `Camera`. Materials are mapped onto the renderer's (see `GltfMaterial`).
+// Animation, skinning, morph targets and texture samplers are ignored.
+#[allow(dead_code)]
+pub struct GltfScene {
+    pub meshes: Vec<TriangleMesh>,
+    pub cameras: Vec<GltfCamera>,
+}
+
+// A perspective camera, placed in world space
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfCamera {
+    pub name: Option<String>,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    // Vertical field of view, in degrees
+    pub vfov: f64,
+    pub aspect_ratio: Option<f64>,
+}
+
+impl GltfCamera {
+    #[allow(dead_code)]
+    pub fn apply(&self, camera: &mut Camera) {
+        // glTF cameras are pinholes, so there's no defocus blur
+        camera.lookfrom = self.lookfrom;
+        camera.lookat = self.lookat;
+        camera.vup = self.vup;
+        camera.vfov = self.vfov;
+        camera.defocus_angle = 0.0;
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera.aspect_ratio = aspect_ratio;
+        }
+    }
+}
+
+impl GltfScene {
+    #[allow(dead_code)]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {message}", path.display()),
+            )
+        };
+
+        let bytes = fs::read(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+        let (json, binary) = if bytes.starts_with(b"glTF") {
+            split_glb(&bytes).map_err(invalid)?
+        } else {
+            let text = String::from_utf8(bytes).map_err(|_| invalid("Not UTF-8".to_string()))?;
+            (text, None)
+        };
+        let root = Json::parse(&json).map_err(|err| invalid(err.to_string()))?;
+
+        let mut loader = GltfLoader::new(&root, path.parent().unwrap_or(Path::new("")), binary)
+            .map_err(invalid)?;
+        loader.load_scene().map_err(invalid)?;
+        Ok(GltfScene {
+            meshes: loader.meshes,
+            cameras: loader.cameras,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(TriangleMesh::triangle_count).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn into_list(self) -> HittableList {
+        // Every triangle of every mesh, ready for `BVHNode::from_list`
+        let mut list = HittableList::default();
+        for mesh in self.meshes {
+            for triangle in mesh.into_list().objects {
+                list.add(triangle);
+            }
+        }
+        list
+    }
+}
+
+#[allow(dead_code)]
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> io::Result<HittableList> {
+    Ok(GltfScene::load(path)?.into_list())
+}
+
+fn split_glb(bytes: &[u8]) -> Result<(String, Option<Vec<u8>>), String> {
+    // 12 byte header (magic, version, length), then chunks of (length, type, data). The JSON
+    // chunk comes first and an optional binary chunk holds buffer 0.
+    const JSON_CHUNK: u32 = 0x4E4F_534A;
+    const BIN_CHUNK: u32 = 0x004E_4942;
+
+    if bytes.len() < 12 || LittleEndian::read_u32(&bytes[4..8]) != 2 {
+        return Err("Only version 2 GLB files are supported".to_string());
+    }
+    let length = (LittleEndian::read_u32(&bytes[8..12]) as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut binary = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = LittleEndian::read_u32(&bytes[offset..offset + 4]) as usize;
+        let chunk_type = LittleEndian::read_u32(&bytes[offset + 4..offset + 8]);
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or("GLB chunk runs past the end of the file")?;
+        match chunk_type {
+            JSON_CHUNK if json.is_none() => {
+                json = Some(String::from_utf8(data.to_vec()).map_err(|_| "GLB JSON isn't UTF-8")?);
+            }
+            BIN_CHUNK if binary.is_none() => binary = Some(data.to_vec()),
+            _ => {}
+        }
+        // Chunks are padded to 4 bytes
+        offset += 8 + chunk_length.next_multiple_of(4);
+    }
+
+    Ok((json.ok_or("GLB has no JSON chunk")?, binary))
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err("Bad base64 data".to_string()),
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn decode_uri(uri: &str) -> String {
+    // Relative URIs may have percent encoded characters (spaces, mostly)
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Texture whose values are scaled by a constant color, for glTF's texture times factor
+struct TintedTexture {
+    texture: Rc<dyn Texture>,
+    tint: Color,
+}
+
+impl Texture for TintedTexture {
+    fn value(&self, u: f64, v: f64, point: Point3) -> Color {
+        self.texture.value(u, v, point) * &self.tint
+    }
+}
+
+// glTF metallic-roughness material, mapped onto the renderer's materials:
+// - emissive (`emissiveFactor` times `KHR_materials_emissive_strength`) becomes a
+//   `DiffuseLight`, textured by `emissiveTexture` if there is one
+// - transmissive (`KHR_materials_transmission` over 0.5) becomes a `Dielectric` with the
+//   `KHR_materials_ior` index
+// - metallic (`metallicFactor` over 0.5) becomes a `Metal` with the base color factor as albedo
+//   and the roughness factor as fuzz
+// - anything else becomes a `Lambertian` with `baseColorTexture` times `baseColorFactor`
+// The metallic-roughness, normal and occlusion textures aren't used.
+struct GltfMaterial {
+    base_color: Color,
+    base_color_texture: Option<usize>,
+    metallic: f64,
+    roughness: f64,
+    emissive: Color,
+    emissive_texture: Option<usize>,
+    transmission: f64,
+    ior: f64,
+}
+
+impl GltfMaterial {
+    fn parse(material: &Json) -> Self {
+        let pbr = material.get("pbrMetallicRoughness");
+        let pbr_field = |key: &str| pbr.and_then(|pbr| pbr.get(key));
+        let extension = |name: &str, key: &str| {
+            material
+                .get("extensions")
+                .and_then(|extensions| extensions.get(name))
+                .and_then(|extension| extension.get(key))
+                .and_then(Json::as_f64)
+        };
+        let texture_index = |info: Option<&Json>| {
+            let info = info?;
+            if info.get("texCoord").and_then(Json::as_usize).unwrap_or(0) != 0 {
+                eprintln!("glTF textures using a second UV set aren't supported");
+            }
+            info.get("index").and_then(Json::as_usize)
+        };
+
+        let [r, g, b, _alpha] = pbr_field("baseColorFactor")
+            .and_then(Json::as_f64_array::<4>)
+            .unwrap_or([1.0; 4]);
+        let [er, eg, eb] = material
+            .get("emissiveFactor")
+            .and_then(Json::as_f64_array::<3>)
+            .unwrap_or([0.0; 3]);
+        let strength =
+            extension("KHR_materials_emissive_strength", "emissiveStrength").unwrap_or(1.0);
+
+        Self {
+            base_color: Color::new(r, g, b),
+            base_color_texture: texture_index(pbr_field("baseColorTexture")),
+            metallic: pbr_field("metallicFactor")
+                .and_then(Json::as_f64)
+                .unwrap_or(1.0),
+            roughness: pbr_field("roughnessFactor")
+                .and_then(Json::as_f64)
+                .unwrap_or(1.0),
+            emissive: Color::new(er, eg, eb) * strength,
+            emissive_texture: texture_index(material.get("emissiveTexture")),
+            transmission: extension("KHR_materials_transmission", "transmissionFactor")
+                .unwrap_or(0.0),
+            ior: extension("KHR_materials_ior", "ior").unwrap_or(1.5),
+        }
+    }
+
+    fn build(&self, loader: &mut GltfLoader) -> Rc<dyn Material> {
+        let mut texture = |index: Option<usize>, factor: Color| -> Rc<dyn Texture> {
+            match index.and_then(|index| loader.texture(index)) {
+                Some(texture) => Rc::new(TintedTexture {
+                    texture,
+                    tint: factor,
+                }),
+                None => Rc::new(SolidColor::new(factor)),
+            }
+        };
+
+        if !self.emissive.near_zero() {
+            return Rc::new(DiffuseLight::new(texture(
+                self.emissive_texture,
+                self.emissive,
+            )));
+        }
+        if self.transmission > 0.5 {
+            return Rc::new(Dielectric::new(self.ior));
+        }
+        if self.metallic > 0.5 {
+            return Rc::new(Metal::new(self.base_color, self.roughness.clamp(0.0, 1.0)));
+        }
+        Rc::new(Lambertian::new(texture(
+            self.base_color_texture,
+            self.base_color,
+        )))
+    }
+}
+
+struct GltfLoader<'a> {
+    root: &'a Json,
+    base_dir: PathBuf,
+    buffers: Vec<Vec<u8>>,
+    materials: Vec<Rc<dyn Material>>,
+    default_material: Rc<dyn Material>,
+    // Decoded images by image index; `None` for images that couldn't be loaded
+    images: HashMap<usize, Option<Rc<dyn Texture>>>,
+    meshes: Vec<TriangleMesh>,
+    cameras: Vec<GltfCamera>,
+}
+
+impl<'a> GltfLoader<'a> {
+    fn new(root: &'a Json, base_dir: &Path, binary: Option<Vec<u8>>) -> Result<Self, String> {
+        let version = root
+            .get("asset")
+            .and_then(|asset| asset.get("version"))
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        if !version.starts_with('2') {
+            return Err(format!("Unsupported glTF version {version:?}"));
+        }
+        if let Some(required) = root.get("extensionsRequired").and_then(Json::as_array) {
+            for extension in required.iter().filter_map(Json::as_str) {
+                eprintln!("glTF extension {extension} is required but not supported");
+            }
+        }
+
+        let mut loader = Self {
+            root,
+            base_dir: base_dir.to_path_buf(),
+            buffers: Vec::new(),
+            materials: Vec::new(),
+            default_material: Rc::new(Lambertian::from_color(Color::new(0.8, 0.8, 0.8))),
+            images: HashMap::new(),
+            meshes: Vec::new(),
+            cameras: Vec::new(),
+        };
+
+        // Buffers without a URI are the GLB binary chunk
+        let mut binary = binary;
+        for buffer in loader.array("buffers") {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => loader.read_uri(uri)?,
+                None => binary.take().ok_or("Buffer has no data")?,
+            };
+            let length = buffer
+                .get("byteLength")
+                .and_then(Json::as_usize)
+                .unwrap_or(0);
+            if data.len() < length {
+                return Err(format!("Buffer is {} bytes, expected {length}", data.len()));
+            }
+            loader.buffers.push(data);
+        }
+
+        let materials: Vec<GltfMaterial> = loader
+            .array("materials")
+            .iter()
+            .map(GltfMaterial::parse)
+            .collect();
+        for material in materials {
+            let built = material.build(&mut loader);
+            loader.materials.push(built);
+        }
+        Ok(loader)
+    }
+
+    fn array(&self, key: &str) -> &'a [Json] {
+        self.root
+            .get(key)
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+    }
+
+    fn item(&self, key: &str, index: usize) -> Result<&'a Json, String> {
+        self.array(key)
+            .get(index)
+            .ok_or_else(|| format!("{key} index {index} out of range"))
+    }
+
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, String> {
+        // Either a base64 data URI or a path relative to the glTF file
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (header, payload) = data.split_once(',').ok_or("Bad data URI")?;
+            if !header.ends_with(";base64") {
+                return Err("Only base64 data URIs are supported".to_string());
+            }
+            return decode_base64(payload);
+        }
+        let path = self.base_dir.join(decode_uri(uri));
+        fs::read(&path).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    fn texture(&mut self, index: usize) -> Option<Rc<dyn Texture>> {
+        // Textures that can't be loaded are reported and left out, like missing OBJ maps
+        let source = self
+            .item("textures", index)
+            .ok()?
+            .get("source")
+            .and_then(Json::as_usize)?;
+        if let Some(texture) = self.images.get(&source) {
+            return texture.clone();
+        }
+
+        let texture = self
+            .load_image(source)
+            .map_err(|err| eprintln!("Couldn't load glTF image {source}: {err}"));
+        let texture = texture
+            .ok()
+            .map(|texture| Rc::new(texture) as Rc<dyn Texture>);
+        self.images.insert(source, texture.clone());
+        texture
+    }
+
+    fn load_image(&self, index: usize) -> Result<ImageTexture, String> {
+        let image = self.item("images", index)?;
+        if let Some(uri) = image.get("uri").and_then(Json::as_str) {
+            if !uri.starts_with("data:") {
+                let path = self.base_dir.join(decode_uri(uri));
+                return ImageTexture::load(&path.to_string_lossy()).map_err(|err| err.to_string());
+            }
+            return ImageTexture::from_memory(&self.read_uri(uri)?).map_err(|err| err.to_string());
+        }
+        let view = image
+            .get("bufferView")
+            .and_then(Json::as_usize)
+            .ok_or("Image has no data")?;
+        let (data, _stride) = self.buffer_view(view)?;
+        ImageTexture::from_memory(data).map_err(|err| err.to_string())
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), String> {
+        let view = self.item("bufferViews", index)?;
+        let buffer = view
+            .get("buffer")
+            .and_then(Json::as_usize)
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or("Buffer view has no buffer")?;
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view
+            .get("byteLength")
+            .and_then(Json::as_usize)
+            .ok_or("Buffer view has no length")?;
+        let data = buffer
+            .get(offset..offset + length)
+            .ok_or("Buffer view runs past the end of its buffer")?;
+        Ok((data, view.get("byteStride").and_then(Json::as_usize)))
+    }
+
+    fn accessor(&self, index: usize) -> Result<(Vec<f64>, usize), String> {
+        // Reads an accessor into a flat list of values, `components` per element, returned
+        // alongside. Normalized integers are mapped to [0,1] or [-1,1].
+        let accessor = self.item("accessors", index)?;
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or("Accessor has no count")?;
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .ok_or("Accessor has no component type")?;
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            other => return Err(format!("Unknown accessor type {other:?}")),
+        };
+
+        // Accessors without a buffer view are all zeros, until sparse values are applied
+        let mut values = match accessor.get("bufferView").and_then(Json::as_usize) {
+            Some(view) => {
+                let offset = accessor
+                    .get("byteOffset")
+                    .and_then(Json::as_usize)
+                    .unwrap_or(0);
+                self.read_elements(view, offset, count, component_type, components, normalized)?
+            }
+            None => vec![0.0; count * components],
+        };
+
+        if let Some(sparse) = accessor.get("sparse") {
+            let sparse_count = sparse.get("count").and_then(Json::as_usize).unwrap_or(0);
+            let location = |what: &str| -> Result<(usize, usize), String> {
+                let part = sparse.get(what).ok_or("Sparse accessor is incomplete")?;
+                let view = part
+                    .get("bufferView")
+                    .and_then(Json::as_usize)
+                    .ok_or("Sparse accessor is incomplete")?;
+                Ok((
+                    view,
+                    part.get("byteOffset").and_then(Json::as_usize).unwrap_or(0),
+                ))
+            };
+            let (view, offset) = location("indices")?;
+            let index_type = sparse
+                .get("indices")
+                .and_then(|indices| indices.get("componentType"))
+                .and_then(Json::as_usize)
+                .ok_or("Sparse accessor is incomplete")?;
+            let indices = self.read_elements(view, offset, sparse_count, index_type, 1, false)?;
+            let (view, offset) = location("values")?;
+            let replacements = self.read_elements(
+                view,
+                offset,
+                sparse_count,
+                component_type,
+                components,
+                normalized,
+            )?;
+            for (&index, replacement) in indices.iter().zip(replacements.chunks_exact(components)) {
+                let start = index as usize * components;
+                values
+                    .get_mut(start..start + components)
+                    .ok_or("Sparse accessor index out of range")?
+                    .copy_from_slice(replacement);
+            }
+        }
+
+        Ok((values, components))
+    }
+
+    fn read_elements(
+        &self,
+        view: usize,
+        offset: usize,
+        count: usize,
+        component_type: usize,
+        components: usize,
+        normalized: bool,
+    ) -> Result<Vec<f64>, String> {
+        let (data, stride) = self.buffer_view(view)?;
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(format!("Unknown component type {component_type}")),
+        };
+        let stride = stride.unwrap_or(size * components);
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * size;
+                let bytes = data
+                    .get(start..start + size)
+                    .ok_or("Accessor runs past the end of its buffer view")?;
+                let value = match component_type {
+                    5120 => {
+                        let value = bytes[0] as i8 as f64;
+                        if normalized {
+                            (value / 127.0).max(-1.0)
+                        } else {
+                            value
+                        }
+                    }
+                    5121 => {
+                        let value = bytes[0] as f64;
+                        if normalized { value / 255.0 } else { value }
+                    }
+                    5122 => {
+                        let value = LittleEndian::read_i16(bytes) as f64;
+                        if normalized {
+                            (value / 32767.0).max(-1.0)
+                        } else {
+                            value
+                        }
+                    }
+                    5123 => {
+                        let value = LittleEndian::read_u16(bytes) as f64;
+                        if normalized { value / 65535.0 } else { value }
+                    }
+                    5125 => LittleEndian::read_u32(bytes) as f64,
+                    _ => LittleEndian::read_f32(bytes) as f64,
+                };
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+
+    fn load_scene(&mut self) -> Result<(), String> {
+        // The default scene, or the first; files without scenes use every root node
+        let scene_index = self.root.get("scene").and_then(Json::as_usize).unwrap_or(0);
+        let roots: Vec<usize> = match self.array("scenes").get(scene_index) {
+            Some(scene) => scene
+                .get("nodes")
+                .and_then(Json::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Json::as_usize)
+                .collect(),
+            None => {
+                let children: Vec<usize> = self
+                    .array("nodes")
+                    .iter()
+                    .filter_map(|node| node.get("children").and_then(Json::as_array))
+                    .flatten()
+                    .filter_map(Json::as_usize)
+                    .collect();
+                (0..self.array("nodes").len())
+                    .filter(|node| !children.contains(node))
+                    .collect()
+            }
+        };
+
+        for root in roots {
+            self.load_node(root, Matrix4::identity(), 0)?;
+        }
+        Ok(())
+    }
+
+    fn load_node(
+        &mut self,
+        index: usize,
+        parent: Matrix4<f64>,
+        depth: usize,
+    ) -> Result<(), String> {
+        // Node hierarchies are trees, the depth limit only guards against broken files
+        if depth > 256 {
+            return Err("Node hierarchy is too deep (or has a cycle)".to_string());
+        }
+        let node = self.item("nodes", index)?;
+        let transform = parent * node_transform(node);
+
+        if let Some(mesh) = node.get("mesh").and_then(Json::as_usize) {
+            self.load_mesh(mesh, &transform)?;
+        }
+        if let Some(camera) = node.get("camera").and_then(Json::as_usize) {
+            self.load_camera(camera, &transform)?;
+        }
+        for child in node
+            .get("children")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Json::as_usize)
+        {
+            self.load_node(child, transform, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn load_mesh(&mut self, index: usize, transform: &Matrix4<f64>) -> Result<(), String> {
+        let mesh = self.item("meshes", index)?;
+        // Normals go through the inverse transpose, and mirroring transforms flip the winding
+        let linear: Matrix3<f64> = transform.fixed_view::<3, 3>(0, 0).into();
+        let normal_matrix = linear
+            .try_inverse()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(linear);
+        let mirrored = linear.determinant() < 0.0;
+
+        for primitive in mesh
+            .get("primitives")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+        {
+            let mode = primitive.get("mode").and_then(Json::as_usize).unwrap_or(4);
+            if !matches!(mode, 4..=6) {
+                eprintln!("Skipping glTF primitive with mode {mode}, only triangles are supported");
+                continue;
+            }
+            let attributes = primitive
+                .get("attributes")
+                .ok_or("Primitive has no attributes")?;
+            let attribute = |name: &str| attributes.get(name).and_then(Json::as_usize);
+
+            let (positions, components) =
+                self.accessor(attribute("POSITION").ok_or("Primitive has no positions")?)?;
+            if components != 3 {
+                return Err("Positions must be VEC3".to_string());
+            }
+            let positions: Vec<Point3> = positions
+                .chunks_exact(3)
+                .map(|p| {
+                    let p = transform.transform_point(&[p[0], p[1], p[2]].into());
+                    Point3::new(p.x, p.y, p.z)
+                })
+                .collect();
+            let vertex_count = positions.len();
+
+            let vertex_indices: Vec<u32> = match primitive.get("indices").and_then(Json::as_usize) {
+                Some(indices) => self
+                    .accessor(indices)?
+                    .0
+                    .iter()
+                    .map(|&i| i as u32)
+                    .collect(),
+                None => (0..vertex_count as u32).collect(),
+            };
+            if vertex_indices.iter().any(|&i| i as usize >= vertex_count) {
+                return Err("Primitive index out of range".to_string());
+            }
+            let mut triangles: Vec<[u32; 3]> = match mode {
+                4 => vertex_indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                // Strips alternate their winding, so every other triangle is swapped back
+                5 => (0..vertex_indices.len().saturating_sub(2))
+                    .map(|i| {
+                        let t = &vertex_indices[i..i + 3];
+                        if i % 2 == 0 {
+                            [t[0], t[1], t[2]]
+                        } else {
+                            [t[1], t[0], t[2]]
+                        }
+                    })
+                    .collect(),
+                _ => (1..vertex_indices.len().saturating_sub(1))
+                    .map(|i| [vertex_indices[0], vertex_indices[i], vertex_indices[i + 1]])
+                    .collect(),
+            };
+            if mirrored {
+                for triangle in &mut triangles {
+                    triangle.swap(1, 2);
+                }
+            }
+
+            let material = match primitive.get("material").and_then(Json::as_usize) {
+                Some(material) => self
+                    .materials
+                    .get(material)
+                    .cloned()
+                    .ok_or_else(|| format!("Material index {material} out of range"))?,
+                None => self.default_material.clone(),
+            };
+            let mut triangle_mesh = TriangleMesh::new(positions, triangles, material);
+
+            if let Some(normals) = attribute("NORMAL") {
+                let normals: Vec<Vec3> = self
+                    .accessor(normals)?
+                    .0
+                    .chunks_exact(3)
+                    .map(|n| {
+                        let n = normal_matrix * Vector3::new(n[0], n[1], n[2]);
+                        Vec3::new(n.x, n.y, n.z)
+                    })
+                    .collect();
+                if normals.len() == vertex_count {
+                    triangle_mesh = triangle_mesh.with_normals(normals);
+                }
+            }
+            if let Some(uvs) = attribute("TEXCOORD_0") {
+                // glTF puts the UV origin at the image's top left, ours is the bottom left
+                let uvs: Vec<[f64; 2]> = self
+                    .accessor(uvs)?
+                    .0
+                    .chunks_exact(2)
+                    .map(|uv| [uv[0], 1.0 - uv[1]])
+                    .collect();
+                if uvs.len() == vertex_count {
+                    triangle_mesh = triangle_mesh.with_uvs(uvs);
+                }
+            }
+            if let Some(colors) = attribute("COLOR_0") {
+                // RGB or RGBA, already linear; alpha is dropped
+                let (colors, components) = self.accessor(colors)?;
+                let colors: Vec<Color> = colors
+                    .chunks_exact(components.max(3))
+                    .map(|c| Color::new(c[0], c[1], c[2]))
+                    .collect();
+                if colors.len() == vertex_count {
+                    triangle_mesh = triangle_mesh.with_colors(colors);
+                }
+            }
+
+            if triangle_mesh.triangle_count() > 0 {
+                self.meshes.push(triangle_mesh);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_camera(&mut self, index: usize, transform: &Matrix4<f64>) -> Result<(), String> {
+        let camera = self.item("cameras", index)?;
+        let Some(perspective) = camera.get("perspective") else {
+            eprintln!("Skipping glTF camera {index}, only perspective cameras are supported");
+            return Ok(());
+        };
+        let yfov = perspective
+            .get("yfov")
+            .and_then(Json::as_f64)
+            .ok_or("Perspective camera has no yfov")?;
+
+        // Cameras look down their local -Z axis with +Y up
+        let to_vec3 = |v: Vector4<f64>| Vec3::new(v.x, v.y, v.z);
+        let lookfrom = to_vec3(transform * Vector4::new(0.0, 0.0, 0.0, 1.0));
+        let forward = to_vec3(transform * Vector4::new(0.0, 0.0, -1.0, 0.0)).unit_vector();
+        let vup = to_vec3(transform * Vector4::new(0.0, 1.0, 0.0, 0.0)).unit_vector();
+
+        self.cameras.push(GltfCamera {
+            name: camera
+                .get("name")
+                .and_then(Json::as_str)
+                .map(str::to_string),
+            lookfrom,
+            lookat: lookfrom + &forward,
+            vup,
+            vfov: yfov.to_degrees(),
+            aspect_ratio: perspective.get("aspectRatio").and_then(Json::as_f64),
+        });
+        Ok(())
+    }
+}
+
+fn node_transform(node: &Json) -> Matrix4<f64> {
+    // Either a column major matrix or translation, rotation (a quaternion) and scale
+    if let Some(matrix) = node.get("matrix").and_then(Json::as_f64_array::<16>) {
+        return Matrix4::from_column_slice(&matrix);
+    }
+    let [tx, ty, tz] = node
+        .get("translation")
+        .and_then(Json::as_f64_array::<3>)
+        .unwrap_or([0.0; 3]);
+    let [x, y, z, w] = node
+        .get("rotation")
+        .and_then(Json::as_f64_array::<4>)
+        .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = node
+        .get("scale")
+        .and_then(Json::as_f64_array::<3>)
+        .unwrap_or([1.0; 3]);
+
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+    Matrix4::new_translation(&Vector3::new(tx, ty, tz))
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(sx, sy, sz))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::hittable::Hittable;
+
+    // One triangle, its positions in buffer 0
+    const TRIANGLE: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+    fn scene_json(buffer: &str) -> String {
+        format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [{{"mesh": 0, "translation": [0, 0, -1]}}],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+                "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+                "buffers": [{{{buffer}"byteLength": 36}}]
+            }}"#
+        )
+    }
+
+    fn triangle_bytes() -> Vec<u8> {
+        TRIANGLE
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn glb(version: u32, chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        for (chunk_type, data) in chunks {
+            let padded = data.len().next_multiple_of(4);
+            bytes.extend((padded as u32).to_le_bytes());
+            bytes.extend(chunk_type.to_le_bytes());
+            bytes.extend(*data);
+            bytes.resize(bytes.len() + padded - data.len(), b' ');
+        }
+        let length = bytes.len() as u32;
+        bytes[8..12].copy_from_slice(&length.to_le_bytes());
+        bytes
+    }
+
+    fn load(name: &str, contents: &[u8]) -> io::Result<GltfScene> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let scene = GltfScene::load(&path);
+        fs::remove_file(&path).unwrap();
+        scene
+    }
+
+    #[test]
+    fn loads_gltf_and_glb_files() {
+        // A .gltf with its buffer in a file next to it
+        let bin = env::temp_dir().join("rusttracer_triangle.bin");
+        fs::write(&bin, triangle_bytes()).unwrap();
+        let json = scene_json(r#""uri": "rusttracer_triangle.bin", "#);
+        let scene = load("rusttracer_triangle.gltf", json.as_bytes());
+        fs::remove_file(&bin).unwrap();
+        let scene = scene.unwrap();
+        assert_eq!(scene.triangle_count(), 1);
+        let bbox = scene.into_list().bounding_box();
+        assert!(bbox.z.contains(-1.0) && !bbox.z.contains(0.0));
+
+        // The same scene as a GLB, detected by its magic whatever the extension
+        let json = scene_json("");
+        let bytes = glb(
+            2,
+            &[
+                (0x4E4F_534A, json.as_bytes()),
+                (0x004E_4942, &triangle_bytes()),
+            ],
+        );
+        let scene = load("rusttracer_triangle.gltf.bin", &bytes).unwrap();
+        assert_eq!(scene.triangle_count(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let json = scene_json("");
+        let error = |name: &str, bytes: &[u8]| load(name, bytes).err().unwrap();
+
+        let err = error(
+            "rusttracer_v1.glb",
+            &glb(1, &[(0x4E4F_534A, json.as_bytes())]),
+        );
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Only version 2 GLB"), "{err}");
+
+        let mut truncated = glb(2, &[(0x4E4F_534A, json.as_bytes())]);
+        truncated.truncate(truncated.len() - 8);
+        let err = error("rusttracer_truncated.glb", &truncated);
+        assert!(err.to_string().contains("runs past the end"), "{err}");
+
+        // A buffer without a URI needs the GLB binary chunk
+        let err = error("rusttracer_no_buffer.gltf", json.as_bytes());
+        assert!(err.to_string().contains("Buffer has no data"), "{err}");
+
+        let err = error(
+            "rusttracer_bad.gltf",
+            b"{\"asset\": {\"version\": \"2.0\"},}",
+        );
+        assert!(err.to_string().contains("at character"), "{err}");
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("AAECAw==").unwrap(), [0, 1, 2, 3]);
+        assert_eq!(decode_base64("/+8\n").unwrap(), [0xFF, 0xEF]);
+        assert!(decode_base64("AA*A").is_err());
+    }
+}
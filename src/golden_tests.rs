@@ -0,0 +1,300 @@
+// Golden image regression tests. Each example scene is rendered small, with few samples and a
+// fixed seed, and compared against a reference image checked in under `tests/golden`. Images are
+// compared after a box blur, so changes that only reshuffle the noise (drawing random numbers in
+// a different order, say) pass while real changes to the picture fail.
+//
+// On failure the render and an amplified difference image are written to `target/golden` for
+// inspection. After an intentional change to the output, regenerate the references with
+//
+//     UPDATE_GOLDEN=1 cargo test golden
+//
+// and check the new images before committing them.
+
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::{
+    exporter::{BMPExporter, Exporter, color_to_bytes},
+    film::Film,
+    progress::SilentProgress,
+    scenes::{self, Scene},
+    utils::seed_rng,
+    vec3::Color,
+};
+
+const IMAGE_WIDTH: i32 = 64;
+const SAMPLES_PER_PIXEL: i32 = 256;
+const MAX_DEPTH: i32 = 10;
+// Scene construction and rendering are seeded separately, so the noise can be changed without
+// changing scenes with random content
+const SCENE_SEED: u64 = 1;
+const RENDER_SEED: u64 = 1;
+
+// Blur radius (in pixels) applied before comparing, and the RMSE allowed between the blurred
+// images, in [0,1] gamma encoded units
+const BLUR_RADIUS: i32 = 4;
+const TOLERANCE: f64 = 0.02;
+
+// The RNG and stat counters are global, so renders can't overlap
+static RENDER_LOCK: Mutex<()> = Mutex::new(());
+
+// The film is inspected directly, nothing needs writing during the render
+struct NullExporter;
+
+impl Exporter for NullExporter {
+    fn export(&mut self, _film: &Film) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 8-bit gamma encoded image, as written by the exporters
+struct Image {
+    width: i32,
+    height: i32,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn from_film(film: &Film) -> Self {
+        let mut pixels = Vec::new();
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                pixels.push(color_to_bytes(film.pixel(x, y)));
+            }
+        }
+        Self {
+            width: film.width(),
+            height: film.height(),
+            pixels,
+        }
+    }
+
+    fn read_bmp(path: &Path) -> io::Result<Self> {
+        // Reads back the 24-bit BMPs written by `BMPExporter`
+        let mut file = io::BufReader::new(fs::File::open(path)?);
+        let mut header = [0; 10];
+        file.read_exact(&mut header)?;
+        let data_offset = file.read_u32::<LittleEndian>()?;
+        let _info_size = file.read_u32::<LittleEndian>()?;
+        let width = file.read_i32::<LittleEndian>()?;
+        let height = file.read_i32::<LittleEndian>()?;
+        let _planes = file.read_u16::<LittleEndian>()?;
+        let bits_per_pixel = file.read_u16::<LittleEndian>()?;
+        if &header[0..2] != b"BM" || bits_per_pixel != 24 || width <= 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a 24-bit BMP",
+            ));
+        }
+        io::copy(
+            &mut (&mut file).take(data_offset as u64 - 30),
+            &mut io::sink(),
+        )?;
+
+        // Rows are stored bottom up unless the height is negative
+        let row_padding = ((4 - (width * 3) % 4) % 4) as usize;
+        let mut rows = Vec::new();
+        for _ in 0..height.abs() {
+            let mut row = vec![0; width as usize * 3 + row_padding];
+            file.read_exact(&mut row)?;
+            rows.push(
+                row.chunks_exact(3)
+                    .take(width as usize)
+                    .map(|bgr| [bgr[2], bgr[1], bgr[0]])
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if height > 0 {
+            rows.reverse();
+        }
+
+        Ok(Self {
+            width,
+            height: height.abs(),
+            pixels: rows.concat(),
+        })
+    }
+
+    fn to_film(&self) -> Film {
+        // Linear film that the exporters will write back out as these pixels
+        let mut film = Film::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let [r, g, b] = self.pixels[(y * self.width + x) as usize];
+                let linear = |c: u8| (c as f64 / 255.0).powi(2);
+                film.add_sample(x, y, Color::new(linear(r), linear(g), linear(b)), 1.0);
+            }
+        }
+        film
+    }
+
+    fn blurred(&self) -> Vec<[f64; 3]> {
+        // Box blur, in [0,1] units
+        let mut blurred = Vec::with_capacity(self.pixels.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = [0.0; 3];
+                let mut count = 0.0;
+                for ny in (y - BLUR_RADIUS).max(0)..(y + BLUR_RADIUS + 1).min(self.height) {
+                    for nx in (x - BLUR_RADIUS).max(0)..(x + BLUR_RADIUS + 1).min(self.width) {
+                        let pixel = self.pixels[(ny * self.width + nx) as usize];
+                        for c in 0..3 {
+                            sum[c] += pixel[c] as f64 / 255.0;
+                        }
+                        count += 1.0;
+                    }
+                }
+                blurred.push(sum.map(|s| s / count));
+            }
+        }
+        blurred
+    }
+
+    fn rmse(&self, other: &Image) -> f64 {
+        let (a, b) = (self.blurred(), other.blurred());
+        let squared: f64 = a
+            .iter()
+            .zip(&b)
+            .flat_map(|(a, b)| (0..3).map(move |c| (a[c] - b[c]).powi(2)))
+            .sum();
+        (squared / (a.len() * 3) as f64).sqrt()
+    }
+
+    fn difference(&self, other: &Image) -> Image {
+        // Per channel absolute difference, amplified so small changes are visible
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .map(|(a, b)| [0, 1, 2].map(|c| a[c].abs_diff(b[c]).saturating_mul(4)))
+            .collect();
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    fn write_bmp(&self, path: &Path) -> io::Result<()> {
+        BMPExporter::new(path)?.export(&self.to_film())
+    }
+}
+
+fn render(build: fn(Box<dyn Exporter>) -> Scene) -> Image {
+    let _lock = RENDER_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    seed_rng(SCENE_SEED);
+    let mut scene = build(Box::new(NullExporter));
+    scene.camera.image_width = IMAGE_WIDTH;
+    scene.camera.samples_per_pixel = SAMPLES_PER_PIXEL;
+    scene.camera.max_depth = scene.camera.max_depth.min(MAX_DEPTH);
+    scene.camera.progress = Box::new(SilentProgress);
+    scene.camera.print_stats = false;
+
+    seed_rng(RENDER_SEED);
+    scene.render();
+    Image::from_film(scene.camera.film())
+}
+
+fn check_golden(name: &str, build: fn(Box<dyn Exporter>) -> Scene) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let reference_path = root.join("tests/golden").join(format!("{name}.bmp"));
+    let actual = render(build);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.write_bmp(&reference_path).unwrap();
+        return;
+    }
+
+    let output_dir: PathBuf = root.join("target/golden");
+    let write_outputs = |reference: Option<&Image>| {
+        fs::create_dir_all(&output_dir).unwrap();
+        actual
+            .write_bmp(&output_dir.join(format!("{name}.actual.bmp")))
+            .unwrap();
+        if let Some(reference) = reference {
+            actual
+                .difference(reference)
+                .write_bmp(&output_dir.join(format!("{name}.diff.bmp")))
+                .unwrap();
+        }
+    };
+
+    let reference = match Image::read_bmp(&reference_path) {
+        Ok(reference) => reference,
+        Err(err) => {
+            write_outputs(None);
+            panic!(
+                "Couldn't read reference {}: {err}. Run with UPDATE_GOLDEN=1 to create it",
+                reference_path.display()
+            );
+        }
+    };
+    if (reference.width, reference.height) != (actual.width, actual.height) {
+        write_outputs(None);
+        panic!(
+            "{name}: rendered {}x{} but the reference is {}x{}",
+            actual.width, actual.height, reference.width, reference.height
+        );
+    }
+
+    let rmse = actual.rmse(&reference);
+    if rmse > TOLERANCE {
+        write_outputs(Some(&reference));
+        panic!(
+            "{name}: differs from the reference (RMSE {rmse:.4} > {TOLERANCE}), see {}",
+            output_dir.display()
+        );
+    }
+}
+
+#[test]
+fn golden_cornell_box() {
+    check_golden("cornell_box", scenes::cornell_box);
+}
+
+#[test]
+fn golden_cornell_smoke() {
+    check_golden("cornell_smoke", scenes::cornell_smoke);
+}
+
+#[test]
+fn golden_simple_lights() {
+    check_golden("simple_lights", scenes::simple_lights);
+}
+
+#[test]
+fn golden_quads() {
+    check_golden("quads", scenes::quads);
+}
+
+#[test]
+fn golden_perlin_spheres() {
+    check_golden("perlin_spheres", scenes::perlin_spheres);
+}
+
+#[test]
+fn golden_earth() {
+    check_golden("earth", scenes::earth);
+}
+
+#[test]
+fn golden_bouncing() {
+    check_golden("bouncing", scenes::bouncing);
+}
+
+#[test]
+fn golden_final_scene() {
+    check_golden("final_scene", |exporter| {
+        scenes::final_scene(exporter, IMAGE_WIDTH, SAMPLES_PER_PIXEL, MAX_DEPTH)
+    });
+}
@@ -0,0 +1,430 @@
+use std::{io, rc::Rc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    perlin::Perlin,
+    ray::Ray,
+    rtw_image::RTWImage,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// Terrain over a regular grid of height samples, spanning `size.x` by `size.z` from `corner` and
+// rising up to `size.y` above it. Each grid cell is two triangles, shaded with normals
+// interpolated from the slopes around their vertices.
+//
+// Rays find their cells through a min-max quadtree over the grid: every node keeps the range of
+// heights beneath it, so rays passing above (or beside) a patch of terrain skip all of its cells
+// at once. Texture coordinates map the whole field to the unit square, lined up with the image a
+// field was loaded from.
+pub struct Heightfield {
+    // Heights above `corner`, row by row along z, `columns` samples along x in each
+    heights: Vec<f64>,
+    columns: usize,
+    rows: usize,
+    corner: Point3,
+    // Size of one cell along x and z
+    cell_x: f64,
+    cell_z: f64,
+    // Height ranges of the cells, then of blocks of 2x2, 4x4... cells, up to the whole field
+    levels: Vec<MinMaxLevel>,
+    bbox: AABB,
+    mat: Rc<dyn Material>,
+}
+
+struct MinMaxLevel {
+    columns: usize,
+    rows: usize,
+    ranges: Vec<Interval>,
+}
+
+// The nearest triangle found so far, in grid coordinates
+struct CellHit {
+    t: f64,
+    vertices: [(usize, usize); 3],
+    weights: [f64; 3],
+    normal: Vec3,
+}
+
+// Undoes the gamma 2.2 that loading applies to 8 and 16-bit images, heightmaps store heights
+// directly. Float (HDR) heightmaps are read as they are.
+const IMAGE_GAMMA: f32 = 2.2;
+// Layers of noise summed into procedural terrain, each twice the frequency of the last
+const NOISE_OCTAVES: i32 = 6;
+
+impl Heightfield {
+    #[allow(dead_code)]
+    pub fn new(
+        heights: Vec<f64>,
+        columns: usize,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        // `heights` go from 0 to 1, row by row, with the samples of a row along x and the rows
+        // along z
+        assert!(
+            columns >= 2 && heights.len() >= 2 * columns && heights.len().is_multiple_of(columns),
+            "Heightfield needs at least 2x2 samples in full rows!"
+        );
+        let rows = heights.len() / columns;
+        let heights: Vec<f64> = heights.iter().map(|h| h * size.y()).collect();
+
+        let levels = Self::build_levels(&heights, columns, rows);
+        let range = levels[levels.len() - 1].ranges[0];
+        let bbox = AABB::new(
+            Interval::new(corner.x(), corner.x() + size.x()),
+            Interval::new(corner.y() + range.min, corner.y() + range.max),
+            Interval::new(corner.z(), corner.z() + size.z()),
+        );
+
+        Self {
+            heights,
+            columns,
+            rows,
+            corner,
+            cell_x: size.x() / (columns - 1) as f64,
+            cell_z: size.z() / (rows - 1) as f64,
+            levels,
+            bbox,
+            mat,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn from_image(
+        path: &str,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> io::Result<Self> {
+        // One sample per pixel, black at the bottom and white (or 1.0, in an HDR image) at the
+        // top; the image's rows run along z. Colors are averaged, and 8-bit images give 256
+        // levels of height.
+        let image = RTWImage::new(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{err}: {path}")))?;
+        let (width, height) = (image.width(), image.height());
+        if width < 2 || height < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Heightfield image needs at least 2x2 pixels: {path}"),
+            ));
+        }
+
+        let gamma = if image.is_hdr() { 1.0 } else { IMAGE_GAMMA };
+        let mut heights = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = image.linear_pixel_at(x, y);
+                let sum: f32 = pixel.iter().map(|c| c.max(0.0).powf(1.0 / gamma)).sum();
+                heights.push((sum / 3.0) as f64);
+            }
+        }
+        Ok(Self::new(heights, width as usize, corner, size, mat))
+    }
+
+    #[allow(dead_code)]
+    pub fn from_noise(
+        noise: &Perlin,
+        frequency: f64,
+        resolution: usize,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        // Fractal noise sampled `resolution` times along each side, with features about
+        // 1 / `frequency` units across, stretched so the lowest point is at the bottom of the
+        // field and the highest at the top
+        let resolution = resolution.max(2);
+        let step = 1.0 / (resolution - 1) as f64;
+        let mut heights = Vec::with_capacity(resolution * resolution);
+        for row in 0..resolution {
+            for column in 0..resolution {
+                let x = corner.x() + size.x() * column as f64 * step;
+                let z = corner.z() + size.z() * row as f64 * step;
+                let mut point = Point3::new(x * frequency, 0.0, z * frequency);
+                let (mut sum, mut weight) = (0.0, 1.0);
+                for _ in 0..NOISE_OCTAVES {
+                    sum += weight * noise.noise(point);
+                    weight *= 0.5;
+                    point *= 2.0;
+                }
+                heights.push(sum);
+            }
+        }
+
+        let low = heights.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = heights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let scale = if high > low { 1.0 / (high - low) } else { 0.0 };
+        for h in &mut heights {
+            *h = (*h - low) * scale;
+        }
+        Self::new(heights, resolution, corner, size, mat)
+    }
+
+    fn build_levels(heights: &[f64], columns: usize, rows: usize) -> Vec<MinMaxLevel> {
+        // Cells span the range of their four corners
+        let (cells_x, cells_z) = (columns - 1, rows - 1);
+        let mut ranges = Vec::with_capacity(cells_x * cells_z);
+        for j in 0..cells_z {
+            for i in 0..cells_x {
+                let corners = [
+                    heights[j * columns + i],
+                    heights[j * columns + i + 1],
+                    heights[(j + 1) * columns + i],
+                    heights[(j + 1) * columns + i + 1],
+                ];
+                let low = corners.iter().copied().fold(f64::INFINITY, f64::min);
+                let high = corners.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                ranges.push(Interval::new(low, high));
+            }
+        }
+        let mut levels = vec![MinMaxLevel {
+            columns: cells_x,
+            rows: cells_z,
+            ranges,
+        }];
+
+        // Each level above merges blocks of up to 2x2 nodes, until one node covers everything
+        loop {
+            let below = &levels[levels.len() - 1];
+            if below.columns == 1 && below.rows == 1 {
+                break;
+            }
+            let (columns, rows) = (below.columns.div_ceil(2), below.rows.div_ceil(2));
+            let mut ranges = vec![Interval::empty(); columns * rows];
+            for j in 0..below.rows {
+                for i in 0..below.columns {
+                    let node = &mut ranges[(j / 2) * columns + i / 2];
+                    *node = Interval::from_intervals(*node, below.ranges[j * below.columns + i]);
+                }
+            }
+            levels.push(MinMaxLevel {
+                columns,
+                rows,
+                ranges,
+            });
+        }
+        levels
+    }
+
+    fn height(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.columns + column]
+    }
+
+    fn vertex_normal(&self, column: usize, row: usize) -> Vec3 {
+        // Central differences inside the field, one-sided ones along its edges
+        let (left, right) = (column.saturating_sub(1), (column + 1).min(self.columns - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.rows - 1));
+        let slope_x = (self.height(right, row) - self.height(left, row))
+            / ((right - left) as f64 * self.cell_x);
+        let slope_z = (self.height(column, front) - self.height(column, back))
+            / ((front - back) as f64 * self.cell_z);
+        Vec3::new(-slope_x, 1.0, -slope_z).unit_vector()
+    }
+
+    fn traverse(
+        &self,
+        level: usize,
+        i: usize,
+        j: usize,
+        ray: &Ray,
+        ray_t: &mut Interval,
+        found: &mut Option<CellHit>,
+    ) {
+        // Skip the node unless the ray passes through the box of its cells and heights
+        let span = 1 << level;
+        let (cells_x, cells_z) = (self.columns - 1, self.rows - 1);
+        let (x0, z0) = (i * span, j * span);
+        let (x1, z1) = ((x0 + span).min(cells_x), (z0 + span).min(cells_z));
+        let nodes = &self.levels[level];
+        let node_box = AABB::new(
+            Interval::new(x0 as f64, x1 as f64),
+            nodes.ranges[j * nodes.columns + i],
+            Interval::new(z0 as f64, z1 as f64),
+        );
+        if node_box.clip(ray, *ray_t).is_none() {
+            return;
+        }
+
+        if level == 0 {
+            self.hit_cell(i, j, ray, ray_t, found);
+            return;
+        }
+
+        // Visit the children nearest the ray's origin first, so hits there cut the ray short
+        // before the others are tried
+        let children = &self.levels[level - 1];
+        let mut columns = [2 * i, 2 * i + 1];
+        let mut rows = [2 * j, 2 * j + 1];
+        if ray.dir.x() < 0.0 {
+            columns.reverse();
+        }
+        if ray.dir.z() < 0.0 {
+            rows.reverse();
+        }
+        for row in rows {
+            for column in columns {
+                if column < children.columns && row < children.rows {
+                    self.traverse(level - 1, column, row, ray, ray_t, found);
+                }
+            }
+        }
+    }
+
+    fn hit_cell(
+        &self,
+        i: usize,
+        j: usize,
+        ray: &Ray,
+        ray_t: &mut Interval,
+        found: &mut Option<CellHit>,
+    ) {
+        // The cell splits along its diagonal from (i, j) to (i + 1, j + 1)
+        let corner = |column: usize, row: usize| {
+            (
+                column,
+                row,
+                Point3::new(column as f64, self.height(column, row), row as f64),
+            )
+        };
+        let triangles = [
+            [corner(i, j), corner(i + 1, j), corner(i + 1, j + 1)],
+            [corner(i, j), corner(i + 1, j + 1), corner(i, j + 1)],
+        ];
+
+        for [a, b, c] in triangles {
+            // Moller-Trumbore
+            let (edge1, edge2) = (b.2 - &a.2, c.2 - &a.2);
+            let p = ray.dir.cross(&edge2);
+            let det = edge1.dot(&p);
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let offset = ray.origin - &a.2;
+            let beta = offset.dot(&p) / det;
+            if !(0.0..=1.0).contains(&beta) {
+                continue;
+            }
+            let q = offset.cross(&edge1);
+            let gamma = ray.dir.dot(&q) / det;
+            if gamma < 0.0 || beta + gamma > 1.0 {
+                continue;
+            }
+            let t = edge2.dot(&q) / det;
+            if !ray_t.surrounds(t) {
+                continue;
+            }
+
+            ray_t.max = t;
+            *found = Some(CellHit {
+                t,
+                vertices: [(a.0, a.1), (b.0, b.1), (c.0, c.1)],
+                weights: [1.0 - beta - gamma, beta, gamma],
+                normal: edge1.cross(&edge2),
+            });
+        }
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        // Work in grid coordinates, with a unit per cell along x and z. Scaling the direction
+        // along with the origin keeps the ray's t.
+        let to_grid = |v: Vec3| Vec3::new(v.x() / self.cell_x, v.y(), v.z() / self.cell_z);
+        let grid_ray = Ray::new(
+            to_grid(ray.origin - &self.corner),
+            to_grid(ray.dir),
+            ray.time,
+        );
+
+        let mut grid_t = ray_t;
+        let mut found = None;
+        self.traverse(
+            self.levels.len() - 1,
+            0,
+            0,
+            &grid_ray,
+            &mut grid_t,
+            &mut found,
+        );
+        let Some(cell_hit) = found else {
+            return false;
+        };
+
+        rec.t = cell_hit.t;
+        rec.p = ray.at(cell_hit.t);
+        rec.mat = Some(self.mat.clone());
+
+        // Normals scale the other way from points, and terrain faces up
+        let mut normal = to_grid(cell_hit.normal);
+        if normal.y() < 0.0 {
+            normal = -normal;
+        }
+        rec.set_face_normal(ray, normal.unit_vector());
+
+        let mut shading_normal = Vec3::default();
+        let (mut column, mut row) = (0.0, 0.0);
+        for (&(i, j), &weight) in cell_hit.vertices.iter().zip(&cell_hit.weights) {
+            shading_normal += &(self.vertex_normal(i, j) * weight);
+            column += i as f64 * weight;
+            row += j as f64 * weight;
+        }
+        rec.set_shading_normal(shading_normal.unit_vector());
+
+        // V is flipped so that row 0, the top of a heightmap image, is at the top of a texture
+        rec.u = column / (self.columns - 1) as f64;
+        rec.v = 1.0 - row / (self.rows - 1) as f64;
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::{material::Lambertian, vec3::Color};
+
+    fn load(name: &str, contents: &[u8]) -> Vec<f64> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let size = Vec3::new(1.0, 1.0, 1.0);
+        let field = Heightfield::from_image(path.to_str().unwrap(), Point3::default(), size, mat);
+        fs::remove_file(&path).unwrap();
+        field.unwrap().heights
+    }
+
+    #[test]
+    fn reads_8_bit_and_hdr_heightmaps() {
+        // 8-bit gray levels are heights as they are, once the loader's gamma is undone
+        let mut ppm = b"P6 2 2 255\n".to_vec();
+        for level in [0u8, 255, 51, 102] {
+            ppm.extend([level; 3]);
+        }
+        let heights = load("rusttracer_heights.ppm", &ppm);
+        for (height, expected) in heights.iter().zip([0.0, 1.0, 0.2, 0.4]) {
+            assert!((height - expected).abs() < 0.005, "{heights:?}");
+        }
+
+        // Float values aren't gamma encoded, and may go above 1. RGBE pixels are a shared
+        // exponent (biased by 136 here, for the 8 bit mantissas) and three mantissas.
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 2\n".to_vec();
+        for [mantissa, exponent] in [[0, 0], [128, 130], [128, 128], [128, 127]] {
+            hdr.extend([mantissa, mantissa, mantissa, exponent]);
+        }
+        let heights = load("rusttracer_heights.hdr", &hdr);
+        assert_eq!(heights, [0.0, 2.0, 0.5, 0.25]);
+    }
+}
@@ -1,17 +1,17 @@
 use std::{f64::INFINITY, rc::Rc};
 
-use derive_new::new as New;
-
 use crate::{
     aabb::AABB,
     interval::Interval,
     material::Material,
     ray::Ray,
     utils::degrees_to_radians,
-    vec3::{Point3, Vec3},
+    vec3::{Color, Point3, Vec3},
 };
 
-#[derive(Clone, New, Default)]
+// Filled in by `Hittable::hit`, starting from `HitRecord::default()`; the normals go through
+// `set_face_normal` and `set_shading_normal`
+#[derive(Clone, Default)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
@@ -20,6 +20,11 @@ pub struct HitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    // The surface's actual normal, on the same side as `normal`. Primitives with interpolated
+    // (shading) normals keep this flat one for deciding which side of the surface a ray is on
+    pub geometric_normal: Vec3,
+    // Interpolated vertex color of meshes that have them, tinting the material's albedo
+    pub vertex_color: Option<Color>,
 }
 
 impl HitRecord {
@@ -34,6 +39,41 @@ impl HitRecord {
         } else {
             outward_normal * -1.0
         };
+        self.geometric_normal = self.normal;
+
+        // Every primitive comes through here, so this also clears the vertex color a farther
+        // hit may have left in the record
+        self.vertex_color = None;
+    }
+
+    #[allow(dead_code)]
+    pub fn set_shading_normal(&mut self, shading_normal: Vec3) {
+        // Replaces the normal with an interpolated one, after `set_face_normal` has decided the
+        // side from the geometric normal. The shading normal is flipped to that same side.
+        // NOTE: the parameter `shading_normal` is assumed to have unit length
+        self.normal = if self.front_face {
+            shading_normal
+        } else {
+            shading_normal * -1.0
+        };
+    }
+
+    #[allow(dead_code)]
+    pub fn keep_on_side(&self, direction: Vec3, outward: bool) -> Vec3 {
+        // Materials scatter around the shading normal, which can send a direction through the
+        // actual surface, where it would hit the same surface again and darken it. Mirror such
+        // directions back across the geometric normal: out to the side the ray came from when
+        // `outward` (reflections), or in through the surface (refractions). Flat shaded hits
+        // are left alone.
+        if (self.normal - &self.geometric_normal).near_zero() {
+            return direction;
+        }
+        let along = direction.dot(&self.geometric_normal);
+        if (outward && along < 0.0) || (!outward && along > 0.0) {
+            direction - &(self.geometric_normal * (2.0 * along))
+        } else {
+            direction
+        }
     }
 }
 
@@ -143,7 +183,7 @@ impl RotateY {
         let mut min = Point3::new(INFINITY, INFINITY, INFINITY);
         let mut max = Point3::new(-INFINITY, -INFINITY, -INFINITY);
 
-        let bbox = AABB::default();
+        let bbox = object.bounding_box();
 
         for i in 0..2 {
             for j in 0..2 {
@@ -165,11 +205,18 @@ impl RotateY {
             }
         }
 
+        // Rotating infinite bounds would mix infinities of both signs into NaNs
+        let bbox = if bbox.is_bounded() {
+            AABB::from_points(min, max)
+        } else {
+            AABB::universe()
+        };
+
         RotateY {
             object,
             sin_theta,
             cos_theta,
-            bbox: AABB::from_points(min, max),
+            bbox,
         }
     }
 }
@@ -210,6 +257,11 @@ impl Hittable for RotateY {
             rec.normal.y(),
             (-sin_theta * rec.normal.x()) + (cos_theta * rec.normal.z()),
         );
+        rec.geometric_normal = Vec3::new(
+            (cos_theta * rec.geometric_normal.x()) + (sin_theta * rec.geometric_normal.z()),
+            rec.geometric_normal.y(),
+            (-sin_theta * rec.geometric_normal.x()) + (cos_theta * rec.geometric_normal.z()),
+        );
 
         return true;
     }
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix3x4, Matrix4, Vector3, Vector4};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    transform::Transform,
+    vec3::Vec3,
+};
+
+// One placement of a shared prototype. The prototype (typically a `BVHNode` over a model) is
+// built once and referenced by every instance, so each instance only costs its inverse matrix,
+// bounding box and a couple of pointers, however big the prototype is. Put the instances in a
+// `BVHNode` of their own for a two level hierarchy.
+//
+// Only the world to object matrix is kept: hit points are found back along the world space ray
+// (t is the same in both spaces) and normals go through its transpose.
+pub struct Instance {
+    prototype: Rc<dyn Hittable>,
+    // Affine rows of the inverse transform
+    to_object: Matrix3x4<f64>,
+    material: Option<Rc<dyn Material>>,
+    bbox: AABB,
+}
+
+impl Instance {
+    #[allow(dead_code)]
+    pub fn new(prototype: Rc<dyn Hittable>, matrix: Matrix4<f64>) -> Self {
+        // `matrix` places the prototype in the world, see the helpers in `transform`
+        let inverse = matrix
+            .try_inverse()
+            .expect("Instance matrix must be invertible!");
+        let bbox = Transform::transform_bbox(&matrix, prototype.bounding_box());
+        Self {
+            prototype,
+            to_object: inverse.fixed_view::<3, 4>(0, 0).into(),
+            material: None,
+            bbox,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_material(mut self, material: Rc<dyn Material>) -> Self {
+        // Renders this instance with `material` instead of the prototype's own materials
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let linear = self.to_object.fixed_view::<3, 3>(0, 0);
+        let origin =
+            self.to_object * Vector4::new(ray.origin.x(), ray.origin.y(), ray.origin.z(), 1.0);
+        let dir = linear * Vector3::new(ray.dir.x(), ray.dir.y(), ray.dir.z());
+        let object_ray = Ray::new(
+            Vec3::new(origin.x, origin.y, origin.z),
+            Vec3::new(dir.x, dir.y, dir.z),
+            ray.time,
+        );
+
+        if !self.prototype.hit(&object_ray, ray_t, rec) {
+            return false;
+        }
+
+        let to_world_normal = |n: Vec3| {
+            let n = linear.transpose() * Vector3::new(n.x(), n.y(), n.z());
+            Vec3::new(n.x, n.y, n.z).unit_vector()
+        };
+        rec.p = ray.at(rec.t);
+        rec.normal = to_world_normal(rec.normal);
+        rec.geometric_normal = to_world_normal(rec.geometric_normal);
+        if let Some(material) = &self.material {
+            rec.mat = Some(material.clone());
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
@@ -35,7 +35,7 @@ impl Interval {
 
     #[allow(dead_code)]
     pub fn size(&self) -> f64 {
-        self.min - self.max
+        self.max - self.min
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,322 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+// Minimal JSON reader, enough for scene formats like glTF. Numbers are all read as f64 and
+// objects keep their keys in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at character {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            offset: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return Err(parser.error("Trailing characters"));
+        }
+        Ok(value)
+    }
+
+    // Accessors return `None` when the value has another type (or the key is missing), so
+    // optional fields can be chained
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|value| *value >= 0.0 && value.fract() == 0.0)
+            .map(|value| value as usize)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64_array<const N: usize>(&self) -> Option<[f64; N]> {
+        // Fixed size numeric arrays, like vectors and matrices
+        let values = self.as_array()?;
+        if values.len() != N {
+            return None;
+        }
+        let mut array = [0.0; N];
+        for (slot, value) in array.iter_mut().zip(values) {
+            *slot = value.as_f64()?;
+        }
+        Some(array)
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            offset: self.offset,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c.is_some() {
+            self.offset += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("Expected '{expected}'"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::String),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("Bad number {text}")))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("Bad escape")),
+                    };
+                    string.push(c);
+                }
+                Some(c) => string.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        // Characters outside the basic plane are escaped as a surrogate pair
+        let code = if (0xD800..0xDC00).contains(&high) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("Bad unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits: String = (0..4).filter_map(|_| self.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| self.error("Bad unicode escape"))
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() {
+        let text = r#" {
+            "asset": {"version": "2.0"},
+            "values": [1, -2.5, 3e2, true, false, null],
+            "name": "a \"quoted\"\tname é😀",
+            "empty": [{}, []]
+        } "#;
+        let json = Json::parse(text).unwrap();
+
+        let version = json.get("asset").and_then(|asset| asset.get("version"));
+        assert_eq!(version.and_then(Json::as_str), Some("2.0"));
+        let values = json.get("values").and_then(Json::as_array).unwrap();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[1].as_f64(), Some(-2.5));
+        assert_eq!(values[2].as_usize(), Some(300));
+        assert_eq!(values[3].as_bool(), Some(true));
+        assert_eq!(values[5], Json::Null);
+        assert_eq!(
+            json.get("name").and_then(Json::as_str),
+            Some("a \"quoted\"\tname \u{e9}\u{1F600}")
+        );
+        assert_eq!(
+            json.get("empty"),
+            Some(&Json::Array(vec![
+                Json::Object(vec![]),
+                Json::Array(vec![])
+            ]))
+        );
+        assert_eq!(json.get("missing"), None);
+
+        // Fixed size arrays need the exact length and numbers only
+        let vector = Json::parse("[1, 2, 3]").unwrap();
+        assert_eq!(vector.as_f64_array::<3>(), Some([1.0, 2.0, 3.0]));
+        assert_eq!(vector.as_f64_array::<4>(), None);
+        assert_eq!(values[0].as_f64_array::<1>(), None);
+    }
+
+    #[test]
+    fn reports_malformed_input() {
+        for (text, message, offset) in [
+            ("[1, 2,]", "Unexpected character", 6),
+            ("{\"key\" 1}", "Expected ':'", 8),
+            ("\"open", "Unterminated string", 5),
+            ("[1] [2]", "Trailing characters", 4),
+            ("[1.2.3]", "Bad number 1.2.3", 6),
+            ("tru", "Expected 'e'", 3),
+            ("", "Unexpected end of input", 0),
+        ] {
+            let err = Json::parse(text).unwrap_err();
+            assert_eq!(err.message, message, "{text:?}");
+            assert_eq!(err.offset, offset, "{text:?}");
+        }
+    }
+}
@@ -24,6 +24,10 @@ pub trait Material {
     fn emitted(&self, _u: f64, _v: f64, _point: Point3) -> Color {
         return Color::new(0.0, 0.0, 0.0);
     }
+
+    fn light_group(&self) -> Option<Rc<str>> {
+        None
+    }
 }
 
 #[derive(Clone, New)]
@@ -46,9 +50,12 @@ impl Material for Lambertian {
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        let scatter_direction = rec.normal + &Vec3::random_unit_vector();
+        let scatter_direction = rec.keep_on_side(rec.normal + &Vec3::random_unit_vector(), true);
         *scattered = Ray::new(rec.p, scatter_direction, ray_in.time);
         *attenuation = self.texture.value(rec.u, rec.v, rec.p);
+        if let Some(vertex_color) = rec.vertex_color {
+            *attenuation = *attenuation * &vertex_color;
+        }
         return true;
     }
 }
@@ -79,9 +86,9 @@ impl Material for Metal {
     ) -> bool {
         let reflected = Vec3::reflect(&ray_in.dir, &rec.normal).unit_vector()
             + &(Vec3::random_unit_vector() * self.fuzz);
-        *scattered = Ray::new(rec.p, reflected, ray_in.time);
+        *scattered = Ray::new(rec.p, rec.keep_on_side(reflected, true), ray_in.time);
         *attenuation = self.albedo;
-        return Vec3::dot(&scattered.dir, &rec.normal) > 0.0;
+        Vec3::dot(&scattered.dir, &rec.geometric_normal) > 0.0
     }
 }
 
@@ -129,9 +136,9 @@ impl Material for Dielectric {
 
         let cannot_refract = ri * sin_theta > 1.0;
         let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > random_double() {
-            unit_direction.reflect(&rec.normal)
+            rec.keep_on_side(unit_direction.reflect(&rec.normal), true)
         } else {
-            unit_direction.refract(&rec.normal, ri)
+            rec.keep_on_side(unit_direction.refract(&rec.normal, ri), false)
         };
 
         *scattered = Ray::new(rec.p, direction, ray_in.time);
@@ -142,6 +149,8 @@ impl Material for Dielectric {
 #[derive(Clone, New)]
 pub struct DiffuseLight {
     pub texture: Rc<dyn Texture>,
+    #[new(default)]
+    pub light_group: Option<Rc<str>>,
 }
 
 impl DiffuseLight {
@@ -149,12 +158,23 @@ impl DiffuseLight {
     pub fn from_color(albedo: Color) -> Self {
         Self::new(Rc::new(SolidColor::new(albedo)))
     }
+
+    #[allow(dead_code)]
+    pub fn with_light_group(mut self, group: &str) -> Self {
+        // Tags the light so its contribution is rendered to its own film layer
+        self.light_group = Some(group.into());
+        self
+    }
 }
 
 impl Material for DiffuseLight {
     fn emitted(&self, u: f64, v: f64, point: Point3) -> Color {
         self.texture.value(u, v, point)
     }
+
+    fn light_group(&self) -> Option<Rc<str>> {
+        self.light_group.clone()
+    }
 }
 
 #[derive(Clone, New)]
@@ -182,3 +202,58 @@ impl Material for Isotropic {
         return true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, interval::Interval, tri::Tri};
+
+    #[test]
+    fn smooth_shaded_scattering_stays_off_the_surface() {
+        // A triangle facing +y with its shading normals tilted 45 degrees towards +x, hit at a
+        // grazing angle heading +x: mirroring about the shading normal points into the surface
+        let tilted = Vec3::new(1.0, 1.0, 0.0);
+        let tri = Tri::from_vertices(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Rc::new(Lambertian::from_color(Color::default())),
+        )
+        .with_normals(tilted, tilted, tilted);
+        let ray = Ray::new(
+            Point3::new(-0.75, 0.05, 0.25),
+            Vec3::new(1.0, -0.05, 0.0),
+            0.0,
+        );
+        let mut rec = HitRecord::default();
+        assert!(tri.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.front_face);
+        assert!(Vec3::reflect(&ray.dir, &rec.normal).y() < 0.0);
+
+        let mut attenuation = Color::default();
+        let mut scattered = Ray::default();
+        assert!(Metal::new(Color::default(), 0.0).scatter(
+            &ray,
+            &rec,
+            &mut attenuation,
+            &mut scattered
+        ));
+        assert!(scattered.dir.y() > 0.0);
+
+        for _ in 0..1000 {
+            Lambertian::from_color(Color::default()).scatter(
+                &ray,
+                &rec,
+                &mut attenuation,
+                &mut scattered,
+            );
+            assert!(scattered.dir.y() >= 0.0);
+        }
+
+        // Dielectrics keep reflections above the surface and refractions below it
+        let direction = Vec3::new(1.0, -0.5, 0.0);
+        assert!(rec.keep_on_side(direction, true).y() > 0.0);
+        assert_eq!(rec.keep_on_side(direction, false).y(), direction.y());
+        assert!(rec.keep_on_side(direction * -1.0, false).y() < 0.0);
+    }
+}
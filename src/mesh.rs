@@ -0,0 +1,312 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    bvh::BVHNode,
+    hittable::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats,
+    vec3::{Color, Point3, Vec3},
+};
+
+// Indexed triangle mesh. Vertex attributes live in shared buffers and each triangle is three
+// indices into them, so a triangle costs 12 bytes here plus a small `MeshTriangle` handle in the
+// BVH. Normals, UVs and colors are optional; when present there must be one per position.
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<[f64; 2]>,
+    colors: Vec<Color>,
+    indices: Vec<[u32; 3]>,
+    mat: Rc<dyn Material>,
+}
+
+impl TriangleMesh {
+    #[allow(dead_code)]
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>, mat: Rc<dyn Material>) -> Self {
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&index| (index as usize) < positions.len()),
+            "Mesh index out of bounds!"
+        );
+        Self {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            indices,
+            mat,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        // Per vertex normals, interpolated across each triangle for smooth shading
+        assert_eq!(
+            normals.len(),
+            self.positions.len(),
+            "Need one normal per vertex!"
+        );
+        self.normals = normals.iter().map(Vec3::unit_vector).collect();
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_uvs(mut self, uvs: Vec<[f64; 2]>) -> Self {
+        // Per vertex texture coordinates, interpolated across each triangle
+        assert_eq!(uvs.len(), self.positions.len(), "Need one UV per vertex!");
+        self.uvs = uvs;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        // Per vertex (linear) colors, interpolated across each triangle and multiplied into the
+        // material's albedo
+        assert_eq!(
+            colors.len(),
+            self.positions.len(),
+            "Need one color per vertex!"
+        );
+        self.colors = colors;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn into_list(self) -> HittableList {
+        // One lightweight hittable per triangle, all sharing this mesh's buffers
+        let mesh = Rc::new(self);
+        let mut list = HittableList::default();
+        for index in 0..mesh.indices.len() {
+            list.add(Rc::new(MeshTriangle {
+                mesh: mesh.clone(),
+                index: index as u32,
+            }));
+        }
+        list
+    }
+
+    #[allow(dead_code)]
+    pub fn into_bvh(self) -> BVHNode {
+        BVHNode::from_list(self.into_list())
+    }
+}
+
+pub fn triangulate(polygon: &[Point3]) -> Vec<[usize; 3]> {
+    // Splits a planar polygon into triangles by ear clipping, returning indices into `polygon`.
+    // Degenerate polygons fall back to a fan.
+    let n = polygon.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Polygon normal by Newell's method, then project onto the plane it's most aligned with
+    let mut normal = Vec3::default();
+    for i in 0..n {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        normal += &Vec3::new(
+            (a.y() - b.y()) * (a.z() + b.z()),
+            (a.z() - b.z()) * (a.x() + b.x()),
+            (a.x() - b.x()) * (a.y() + b.y()),
+        );
+    }
+    let axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap_or(2);
+    let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+    let points: Vec<[f64; 2]> = polygon
+        .iter()
+        .map(|point| [point[u_axis], point[v_axis]])
+        .collect();
+    // Counter-clockwise in the projection when the normal points along +axis
+    let orientation = normal[axis].signum();
+
+    let cross = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+        ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) * orientation
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            );
+            if cross(points[a], points[b], points[c]) <= 0.0 {
+                return false;
+            }
+            // No other vertex may lie inside the ear
+            remaining.iter().all(|&p| {
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(points[a], points[b], points[p]) < 0.0
+                    || cross(points[b], points[c], points[p]) < 0.0
+                    || cross(points[c], points[a], points[p]) < 0.0
+            })
+        });
+
+        let Some(i) = ear else {
+            // Degenerate or self-intersecting, fan out whatever is left
+            for i in 1..remaining.len() - 1 {
+                triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+            }
+            return triangles;
+        };
+        triangles.push([
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+// A single triangle of a `TriangleMesh`
+pub struct MeshTriangle {
+    mesh: Rc<TriangleMesh>,
+    index: u32,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [usize; 3] {
+        self.mesh.indices[self.index as usize].map(|index| index as usize)
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        // Möller–Trumbore: solve for the hit point's barycentric coordinates directly
+        let [i0, i1, i2] = self.vertices();
+        let p0 = self.mesh.positions[i0];
+        let edge_1 = self.mesh.positions[i1] - &p0;
+        let edge_2 = self.mesh.positions[i2] - &p0;
+
+        let p = ray.dir.cross(&edge_2);
+        let det = edge_1.dot(&p);
+
+        // No hit if the ray is parallel to the triangle
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin - &p0;
+        let b1 = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+
+        let q = s.cross(&edge_1);
+        let b2 = ray.dir.dot(&q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let t = edge_2.dot(&q) * inv_det;
+        if !ray_t.contains(t) {
+            return false;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        rec.t = t;
+        rec.p = ray.at(t);
+        rec.mat = Some(self.mesh.mat.clone());
+
+        // Texture coordinates default to the barycentric coordinates, like `Tri`
+        if self.mesh.uvs.is_empty() {
+            rec.u = b1;
+            rec.v = b2;
+        } else {
+            let [uv0, uv1, uv2] = [i0, i1, i2].map(|i| self.mesh.uvs[i]);
+            rec.u = b0 * uv0[0] + b1 * uv1[0] + b2 * uv2[0];
+            rec.v = b0 * uv0[1] + b1 * uv1[1] + b2 * uv2[1];
+        }
+
+        // Which side was hit is decided by the geometric normal; an interpolated normal is then
+        // flipped to the same side
+        let geometric_normal = edge_1.cross(&edge_2).unit_vector();
+        rec.set_face_normal(ray, geometric_normal);
+        if !self.mesh.normals.is_empty() {
+            let [n0, n1, n2] = [i0, i1, i2].map(|i| self.mesh.normals[i]);
+            rec.set_shading_normal((n0 * b0 + &(n1 * b1) + &(n2 * b2)).unit_vector());
+        }
+        if !self.mesh.colors.is_empty() {
+            let [c0, c1, c2] = [i0, i1, i2].map(|i| self.mesh.colors[i]);
+            rec.vertex_color = Some(c0 * b0 + &(c1 * b1) + &(c2 * b2));
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+        AABB::from_aabbs(
+            AABB::from_points(positions[i0], positions[i1]),
+            AABB::from_points(positions[i2], positions[i2]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(a: Point3, b: Point3, c: Point3) -> f64 {
+        // Signed area of the triangle seen from +z
+        ((b.x() - a.x()) * (c.y() - a.y()) - (b.y() - a.y()) * (c.x() - a.x())) / 2.0
+    }
+
+    #[test]
+    fn triangulate_concave_polygon() {
+        // An L shape, concave at (1, 1)
+        let polygon = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(1.0, 2.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ];
+        let triangles = triangulate(&polygon);
+        assert_eq!(triangles.len(), 4);
+
+        // Every triangle keeps the polygon's winding, and together they cover its area
+        let mut total = 0.0;
+        for [a, b, c] in triangles {
+            let area = area(polygon[a], polygon[b], polygon[c]);
+            assert!(area > 0.0);
+            total += area;
+        }
+        assert!((total - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn triangulate_degenerate_polygons() {
+        assert!(triangulate(&[Point3::default(), Point3::new(1.0, 0.0, 0.0)]).is_empty());
+
+        // Collinear points have no ears, and fall back to a fan
+        let line: Vec<Point3> = (0..5).map(|i| Point3::new(i as f64, 0.0, 0.0)).collect();
+        assert_eq!(triangulate(&line), vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    hittable::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::{TriangleMesh, triangulate},
+    texture::{ImageTexture, SolidColor, Texture},
+    vec3::{Color, Point3, Vec3},
+};
+
+// Wavefront OBJ importer. Faces are split into one `TriangleMesh` per group and material, as a
+// mesh shares a single material. Polygons are triangulated by ear clipping, so concave faces
+// work too. Materials come from the MTL libraries named by `mtllib` (see `MtlMaterial`); faces
+// without a known material get a plain grey `Lambertian`.
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+}
+
+#[allow(dead_code)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: TriangleMesh,
+}
+
+impl ObjModel {
+    #[allow(dead_code)]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let mut parser = ObjParser::new(path.parent().unwrap_or(Path::new("")));
+
+        for (number, line) in contents.lines().enumerate() {
+            parser.parse_line(line).map_err(|message| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {message}", path.display(), number + 1),
+                )
+            })?;
+        }
+
+        Ok(parser.finish())
+    }
+
+    #[allow(dead_code)]
+    pub fn triangle_count(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.mesh.triangle_count())
+            .sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn into_list(self) -> HittableList {
+        // Every triangle of every group, ready for `BVHNode::from_list`
+        let mut list = HittableList::default();
+        for group in self.groups {
+            for triangle in group.mesh.into_list().objects {
+                list.add(triangle);
+            }
+        }
+        list
+    }
+}
+
+#[allow(dead_code)]
+pub fn load_obj<P: AsRef<Path>>(path: P) -> io::Result<HittableList> {
+    Ok(ObjModel::load(path)?.into_list())
+}
+
+// Faces gathered for one group and material. OBJ indexes positions, UVs and normals separately,
+// so each distinct combination becomes one mesh vertex.
+struct GroupBuilder {
+    name: String,
+    material: Option<String>,
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<[f64; 2]>,
+    indices: Vec<[u32; 3]>,
+    vertices: HashMap<FaceVertex, u32>,
+    // Attributes are only kept if every vertex of the group has them
+    all_normals: bool,
+    all_uvs: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct ObjParser {
+    directory: PathBuf,
+    positions: Vec<Point3>,
+    uvs: Vec<[f64; 2]>,
+    normals: Vec<Vec3>,
+    materials: HashMap<String, Rc<dyn Material>>,
+    default_material: Rc<dyn Material>,
+    group: String,
+    material: Option<String>,
+    builders: Vec<GroupBuilder>,
+    builder_index: HashMap<(String, Option<String>), usize>,
+}
+
+impl ObjParser {
+    fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            materials: HashMap::new(),
+            default_material: Rc::new(Lambertian::from_color(Color::new(0.8, 0.8, 0.8))),
+            group: "default".to_string(),
+            material: None,
+            builders: Vec::new(),
+            builder_index: HashMap::new(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            return Ok(());
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats::<3>(&args, 3)?;
+                self.positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_floats::<2>(&args, 1)?;
+                self.uvs.push([u, v]);
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats::<3>(&args, 3)?;
+                self.normals.push(Vec3::new(x, y, z));
+            }
+            "f" => self.parse_face(&args)?,
+            "g" | "o" => {
+                self.group = if args.is_empty() {
+                    "default".to_string()
+                } else {
+                    args.join(" ")
+                };
+            }
+            "usemtl" => self.material = args.first().map(|name| name.to_string()),
+            "mtllib" => {
+                for library in args {
+                    let path = self.directory.join(library);
+                    match MtlMaterial::load_library(&path) {
+                        Ok(materials) => {
+                            for (name, material) in materials {
+                                self.materials.insert(name, material.build(&path));
+                            }
+                        }
+                        Err(err) => {
+                            eprintln!("Couldn't load material library {}: {err}", path.display())
+                        }
+                    }
+                }
+            }
+            // Smoothing groups, lines, points, free-form geometry, ... aren't supported
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_face(&mut self, args: &[&str]) -> Result<(), String> {
+        if args.len() < 3 {
+            return Err("Face needs at least three vertices".to_string());
+        }
+
+        let face = args
+            .iter()
+            .map(|arg| self.parse_face_vertex(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let polygon: Vec<Point3> = face
+            .iter()
+            .map(|vertex| self.positions[vertex.position])
+            .collect();
+
+        let key = (self.group.clone(), self.material.clone());
+        let builder_index = match self.builder_index.get(&key) {
+            Some(&index) => index,
+            None => {
+                self.builders.push(GroupBuilder {
+                    name: key.0.clone(),
+                    material: key.1.clone(),
+                    positions: Vec::new(),
+                    normals: Vec::new(),
+                    uvs: Vec::new(),
+                    indices: Vec::new(),
+                    vertices: HashMap::new(),
+                    all_normals: true,
+                    all_uvs: true,
+                });
+                self.builder_index.insert(key, self.builders.len() - 1);
+                self.builders.len() - 1
+            }
+        };
+        let builder = &mut self.builders[builder_index];
+
+        let mut indices = Vec::with_capacity(face.len());
+        for vertex in face {
+            let index = match builder.vertices.get(&vertex) {
+                Some(&index) => index,
+                None => {
+                    builder.positions.push(self.positions[vertex.position]);
+                    match vertex.normal {
+                        Some(normal) => builder.normals.push(self.normals[normal]),
+                        None => builder.all_normals = false,
+                    }
+                    match vertex.uv {
+                        Some(uv) => builder.uvs.push(self.uvs[uv]),
+                        None => builder.all_uvs = false,
+                    }
+                    let index = (builder.positions.len() - 1) as u32;
+                    builder.vertices.insert(vertex, index);
+                    index
+                }
+            };
+            indices.push(index);
+        }
+
+        for [a, b, c] in triangulate(&polygon) {
+            builder.indices.push([indices[a], indices[b], indices[c]]);
+        }
+        Ok(())
+    }
+
+    fn parse_face_vertex(&self, arg: &str) -> Result<FaceVertex, String> {
+        // One of `p`, `p/t`, `p//n` or `p/t/n`, with 1-based or negative (relative) indices
+        let mut parts = arg.split('/');
+        let resolve =
+            |part: Option<&str>, count: usize, what: &str| -> Result<Option<usize>, String> {
+                let Some(part) = part.filter(|part| !part.is_empty()) else {
+                    return Ok(None);
+                };
+                let index: i64 = part
+                    .parse()
+                    .map_err(|_| format!("Bad {what} index {part}"))?;
+                let resolved = if index > 0 {
+                    index - 1
+                } else {
+                    count as i64 + index
+                };
+                if index == 0 || resolved < 0 || resolved >= count as i64 {
+                    return Err(format!("{what} index {index} out of range"));
+                }
+                Ok(Some(resolved as usize))
+            };
+
+        let position = resolve(parts.next(), self.positions.len(), "Vertex")?
+            .ok_or_else(|| format!("Face vertex {arg} has no position"))?;
+        let uv = resolve(parts.next(), self.uvs.len(), "Texture coordinate")?;
+        let normal = resolve(parts.next(), self.normals.len(), "Normal")?;
+        Ok(FaceVertex {
+            position,
+            uv,
+            normal,
+        })
+    }
+
+    fn finish(self) -> ObjModel {
+        let groups = self
+            .builders
+            .into_iter()
+            .filter(|builder| !builder.indices.is_empty())
+            .map(|builder| {
+                let material = match &builder.material {
+                    Some(name) => self.materials.get(name).cloned().unwrap_or_else(|| {
+                        eprintln!("Unknown OBJ material {name}, using the default");
+                        self.default_material.clone()
+                    }),
+                    None => self.default_material.clone(),
+                };
+
+                let mut mesh = TriangleMesh::new(builder.positions, builder.indices, material);
+                if builder.all_normals {
+                    mesh = mesh.with_normals(builder.normals);
+                }
+                if builder.all_uvs {
+                    mesh = mesh.with_uvs(builder.uvs);
+                }
+                ObjGroup {
+                    name: builder.name,
+                    material: builder.material,
+                    mesh,
+                }
+            })
+            .collect();
+        ObjModel { groups }
+    }
+}
+
+fn parse_floats<const N: usize>(args: &[&str], required: usize) -> Result<[f64; N], String> {
+    // Parses up to N numbers, of which the first `required` must be present (the rest default
+    // to zero); extra values are ignored
+    if args.len() < required {
+        return Err(format!("Expected {required} numbers"));
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| format!("Bad number {arg}"))?;
+    }
+    Ok(values)
+}
+
+// Material as described in an MTL file. These are mapped onto the renderer's materials:
+// - emissive (`Ke` or `map_Ke`) becomes a `DiffuseLight`
+// - transparent (`d` < 1, `Tr` > 0 or illum 4, 6, 7 or 9) becomes a `Dielectric` with index `Ni`
+// - metallic (`Pm` > 0.5 or illum 3 or 5) becomes a `Metal`, colored by `Ks` (or `Kd` with
+//   `Pm`), with its fuzz from `Pr` or the specular exponent `Ns`
+// - anything else becomes a `Lambertian` with `map_Kd` or `Kd`
+#[derive(Debug, Clone, Default)]
+struct MtlMaterial {
+    kd: Option<Color>,
+    ks: Option<Color>,
+    ke: Option<Color>,
+    ns: Option<f64>,
+    ni: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<u32>,
+    metallic: Option<f64>,
+    roughness: Option<f64>,
+    map_kd: Option<String>,
+    map_ke: Option<String>,
+}
+
+impl MtlMaterial {
+    fn load_library(path: &Path) -> io::Result<Vec<(String, MtlMaterial)>> {
+        let contents = fs::read_to_string(path)?;
+        let mut materials: Vec<(String, MtlMaterial)> = Vec::new();
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let args: Vec<&str> = tokens.collect();
+            let invalid = |message: String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {message}", path.display(), number + 1),
+                )
+            };
+
+            if keyword == "newmtl" {
+                let name = args.join(" ");
+                materials.push((name, MtlMaterial::default()));
+                continue;
+            }
+            let Some((_, material)) = materials.last_mut() else {
+                continue;
+            };
+
+            let color = || {
+                parse_floats::<3>(&args, 1)
+                    .map(|[r, g, b]| {
+                        // A single value means grey
+                        if args.len() < 3 {
+                            Color::new(r, r, r)
+                        } else {
+                            Color::new(r, g, b)
+                        }
+                    })
+                    .map_err(invalid)
+            };
+            let number = || {
+                parse_floats::<1>(&args, 1)
+                    .map(|[value]| value)
+                    .map_err(invalid)
+            };
+            // Map statements can have options before the file name, which comes last
+            let map = || args.last().map(|name| name.to_string());
+
+            match keyword {
+                "Kd" => material.kd = Some(color()?),
+                "Ks" => material.ks = Some(color()?),
+                "Ke" => material.ke = Some(color()?),
+                "Ns" => material.ns = Some(number()?),
+                "Ni" => material.ni = Some(number()?),
+                "d" => material.dissolve = Some(number()?),
+                "Tr" => material.dissolve = Some(1.0 - number()?),
+                "illum" => material.illum = Some(number()? as u32),
+                "Pm" => material.metallic = Some(number()?),
+                "Pr" => material.roughness = Some(number()?),
+                "map_Kd" => material.map_kd = map(),
+                "map_Ke" => material.map_ke = map(),
+                _ => {}
+            }
+        }
+        Ok(materials)
+    }
+
+    fn texture(&self, library: &Path, map: &Option<String>, color: Color) -> Rc<dyn Texture> {
+        // Image maps are relative to the material library; unreadable ones fall back to the
+        // plain color
+        if let Some(map) = map {
+            let path = library.parent().unwrap_or(Path::new("")).join(map);
+            match ImageTexture::load(&path.to_string_lossy()) {
+                Ok(texture) => return Rc::new(texture),
+                Err(err) => eprintln!("Couldn't load texture map: {err}"),
+            }
+        }
+        Rc::new(SolidColor::new(color))
+    }
+
+    fn build(&self, library: &Path) -> Rc<dyn Material> {
+        let kd = self.kd.unwrap_or(Color::new(0.8, 0.8, 0.8));
+        let ke = self.ke.unwrap_or_default();
+        let illum = self.illum.unwrap_or(2);
+
+        if !ke.near_zero() || self.map_ke.is_some() {
+            let emission = if self.ke.is_some() {
+                ke
+            } else {
+                Color::new(1.0, 1.0, 1.0)
+            };
+            return Rc::new(DiffuseLight::new(self.texture(
+                library,
+                &self.map_ke,
+                emission,
+            )));
+        }
+
+        if self.dissolve.is_some_and(|dissolve| dissolve < 1.0) || matches!(illum, 4 | 6 | 7 | 9) {
+            return Rc::new(Dielectric::new(self.ni.unwrap_or(1.5)));
+        }
+
+        let metallic = self.metallic.is_some_and(|metallic| metallic > 0.5);
+        if metallic || matches!(illum, 3 | 5) {
+            let albedo = if metallic { kd } else { self.ks.unwrap_or(kd) };
+            // Phong exponents map roughly onto roughness as sqrt(2 / (Ns + 2))
+            let fuzz = match (self.roughness, self.ns) {
+                (Some(roughness), _) => roughness,
+                (None, Some(ns)) => (2.0 / (ns.max(0.0) + 2.0)).sqrt(),
+                (None, None) => 0.0,
+            };
+            return Rc::new(Metal::new(albedo, fuzz.clamp(0.0, 1.0)));
+        }
+
+        Rc::new(Lambertian::new(self.texture(library, &self.map_kd, kd)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn parse(source: &str) -> Result<ObjModel, String> {
+        let mut parser = ObjParser::new(Path::new(""));
+        for line in source.lines() {
+            parser.parse_line(line)?;
+        }
+        Ok(parser.finish())
+    }
+
+    #[test]
+    fn parses_groups_and_relative_indices() {
+        // A quad, then a triangle in a second group using relative indices
+        let model = parse(
+            "# two squares\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             g front\n\
+             f 1/1 2/2 3/3 4/4\n\
+             g back\n\
+             usemtl missing\n\
+             f -1/-1 -2/-2 -3/-3\n",
+        )
+        .unwrap();
+
+        assert_eq!(model.groups.len(), 2);
+        assert_eq!(model.groups[0].name, "front");
+        assert_eq!(model.groups[0].mesh.triangle_count(), 2);
+        assert_eq!(model.groups[1].name, "back");
+        assert_eq!(model.groups[1].material.as_deref(), Some("missing"));
+        assert_eq!(model.triangle_count(), 3);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let err = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n").err().unwrap();
+        assert!(err.contains("out of range"), "{err}");
+        assert!(parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0\n").is_err());
+        assert!(parse("v 0 0\n").is_err());
+    }
+
+    #[test]
+    fn load_reports_the_bad_line() {
+        let path = env::temp_dir().join("rusttracer_bad.obj");
+        fs::write(&path, "v 0 0 0\nv 1 0 0\nf 1 2 x\n").unwrap();
+        let err = ObjModel::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(":3: "), "{err}");
+    }
+}
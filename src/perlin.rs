@@ -43,17 +43,18 @@ impl Perlin {
         let v = point.y() - point.y().floor();
         let w = point.z() - point.z().floor();
 
-        let i = point.x().floor() as i32 as usize;
-        let j = point.y().floor() as i32 as usize;
-        let k = point.z().floor() as i32 as usize;
+        // Lattice cell, wrapped onto the permutation tables (negative coordinates included)
+        let i = point.x().floor() as i32;
+        let j = point.y().floor() as i32;
+        let k = point.z().floor() as i32;
         let mut c = [[[Vec3::default(); 2]; 2]; 2];
 
         for di in 0..2 {
             for dj in 0..2 {
                 for dk in 0..2 {
-                    c[di][dj][dk] = self.randvec[(self.perm_x[(i + di) & 0xFF]
-                        ^ self.perm_y[(j + dj) & 0xFF]
-                        ^ self.perm_z[(k + dk) & 0xFF])
+                    c[di][dj][dk] = self.randvec[(self.perm_x[((i + di as i32) & 0xFF) as usize]
+                        ^ self.perm_y[((j + dj as i32) & 0xFF) as usize]
+                        ^ self.perm_z[((k + dk as i32) & 0xFF) as usize])
                         as usize];
                 }
             }
@@ -0,0 +1,234 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// Flat primitives share everything but the test for whether a point of their plane is inside
+// them. A `Planar` spans the plane from a corner `q` along the edge vectors `u` and `v`, and hands
+// hit points to its shape as plane coordinates: `q + alpha u + beta v`.
+//
+// `Quad`, `Tri` and `Disk` are planar shapes, along with the ellipses and polygons below.
+pub trait PlanarShape {
+    // Given the hit point in plane coordinates, return false if it is outside the shape,
+    // otherwise set the hit record UV coordinates and return true
+    fn is_interior(&self, alpha: f64, beta: f64, rec: &mut HitRecord) -> bool;
+
+    // Bounds of the shape when laid out at `q` along `u` and `v`
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> AABB;
+
+    // Last touches to a hit record once it's filled in, e.g. interpolated normals
+    fn finish_hit(&self, _rec: &mut HitRecord) {}
+}
+
+pub struct Planar<S: PlanarShape> {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    normal: Vec3,
+    d: f64,
+    pub(crate) shape: S,
+}
+
+impl<S: PlanarShape> Planar<S> {
+    #[allow(dead_code)]
+    pub fn from_shape(shape: S, q: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Self {
+        let n = u.cross(&v);
+        let normal = n.unit_vector();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(&n),
+            mat,
+            bbox: shape.bounding_box(q, u, v),
+            normal,
+            d: normal.dot(&q),
+            shape,
+        }
+    }
+}
+
+impl<S: PlanarShape> Hittable for Planar<S> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        let denom = self.normal.dot(&ray.dir);
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // Return false if the hit point parameter t is outside the ray interval
+        let t = (self.d - self.normal.dot(&ray.origin)) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // Determine if the hit point lies within the planar shape using its plane coordinates
+        let intersection = ray.at(t);
+        let planar_hitpt_vec = intersection - &self.q;
+        let alpha = self.w.dot(&planar_hitpt_vec.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar_hitpt_vec));
+
+        if !self.shape.is_interior(alpha, beta, rec) {
+            return false;
+        }
+
+        // Ray hits the 2D shape; set the rest of the hit record and return true
+        rec.t = t;
+        rec.p = intersection;
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(ray, self.normal);
+        self.shape.finish_hit(rec);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+// Ellipse centered on `q`, with `u` and `v` as its two semi-axes (a circle when they're
+// perpendicular and the same length). Texture coordinates map its bounding parallelogram to the
+// unit square.
+pub struct Ellipse;
+
+impl Planar<Ellipse> {
+    #[allow(dead_code)]
+    pub fn ellipse(center: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Self {
+        Self::from_shape(Ellipse, center, u, v, mat)
+    }
+}
+
+impl PlanarShape for Ellipse {
+    fn is_interior(&self, alpha: f64, beta: f64, rec: &mut HitRecord) -> bool {
+        if alpha * alpha + beta * beta > 1.0 {
+            return false;
+        }
+
+        rec.u = alpha / 2.0 + 0.5;
+        rec.v = beta / 2.0 + 0.5;
+        true
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> AABB {
+        ellipse_bounds(q, u, v)
+    }
+}
+
+#[allow(dead_code)]
+pub fn ellipse_bounds(center: Point3, u: Vec3, v: Vec3) -> AABB {
+    // The points center + cos(theta) u + sin(theta) v reach out to sqrt(u_i^2 + v_i^2) along
+    // each axis i
+    let extent = Vec3::new(
+        (u.x() * u.x() + v.x() * v.x()).sqrt(),
+        (u.y() * u.y() + v.y() * v.y()).sqrt(),
+        (u.z() * u.z() + v.z() * v.z()).sqrt(),
+    );
+    AABB::from_points(center - &extent, center + &extent)
+}
+
+// Any simple polygon, convex or not, given by its vertices in order around its edge. Points
+// are inside by the even-odd rule, so self-intersecting outlines get holes where they overlap.
+// The normal follows the winding (counter-clockwise seen from the front), and texture
+// coordinates map the polygon's bounding rectangle in its plane to the unit square.
+pub struct Polygon {
+    // Vertices in plane coordinates, which span the bounding rectangle from 0 to 1
+    vertices: Vec<[f64; 2]>,
+}
+
+impl Planar<Polygon> {
+    #[allow(dead_code)]
+    pub fn polygon(points: &[Point3], mat: Rc<dyn Material>) -> Self {
+        assert!(points.len() >= 3, "Polygon needs at least three vertices!");
+
+        // Twice the area vector, which also works for concave polygons (Newell's method)
+        let mut area = Vec3::default();
+        for (i, p) in points.iter().enumerate() {
+            area += &p.cross(&points[(i + 1) % points.len()]);
+        }
+        assert!(
+            area.len() > 0.0,
+            "Polygon vertices must not all lie on a line!"
+        );
+
+        // Lay out the plane over the bounding rectangle of the vertices
+        let (u_axis, v_axis) = area.unit_vector().orthonormal_basis();
+        let origin = points[0];
+        let flat: Vec<[f64; 2]> = points
+            .iter()
+            .map(|p| {
+                let offset = *p - &origin;
+                [offset.dot(&u_axis), offset.dot(&v_axis)]
+            })
+            .collect();
+        let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+        for vertex in &flat {
+            for c in 0..2 {
+                min[c] = min[c].min(vertex[c]);
+                max[c] = max[c].max(vertex[c]);
+            }
+        }
+
+        let q = origin + &(u_axis * min[0]) + &(v_axis * min[1]);
+        let u = u_axis * (max[0] - min[0]);
+        let v = v_axis * (max[1] - min[1]);
+        let vertices = flat
+            .iter()
+            .map(|p| {
+                [
+                    (p[0] - min[0]) / (max[0] - min[0]),
+                    (p[1] - min[1]) / (max[1] - min[1]),
+                ]
+            })
+            .collect();
+
+        Self::from_shape(Polygon { vertices }, q, u, v, mat)
+    }
+}
+
+impl PlanarShape for Polygon {
+    fn is_interior(&self, alpha: f64, beta: f64, rec: &mut HitRecord) -> bool {
+        // Count the edges crossing a ray from the point towards +alpha
+        let mut inside = false;
+        let mut previous = self.vertices[self.vertices.len() - 1];
+        for &current in &self.vertices {
+            if (current[1] > beta) != (previous[1] > beta) {
+                let crossing = current[0]
+                    + (beta - current[1]) / (previous[1] - current[1]) * (previous[0] - current[0]);
+                if alpha < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = current;
+        }
+        if !inside {
+            return false;
+        }
+
+        rec.u = alpha;
+        rec.v = beta;
+        true
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> AABB {
+        let mut bbox = AABB::empty();
+        for vertex in &self.vertices {
+            let p = q + &(u * vertex[0]) + &(v * vertex[1]);
+            bbox = AABB::from_aabbs(bbox, AABB::from_points(p, p));
+        }
+        bbox
+    }
+}
@@ -0,0 +1,81 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// Infinite plane through `point`, facing along `normal`, e.g. an exact ground plane instead of a
+// huge sphere. Its bounding box is the whole space; `BVHNode::from_list` keeps such unbounded
+// objects out of the hierarchy so they don't swallow every node's bounds.
+//
+// Texture coordinates are planar: distances along two axes in the plane, divided by the texture
+// size and wrapped to [0,1) so image textures repeat as tiles.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    u_axis: Vec3,
+    v_axis: Vec3,
+    texture_size: f64,
+    mat: Rc<dyn Material>,
+}
+
+impl Plane {
+    #[allow(dead_code)]
+    pub fn new(point: Point3, normal: Vec3, mat: Rc<dyn Material>) -> Self {
+        let normal = normal.unit_vector();
+        let (u_axis, v_axis) = normal.orthonormal_basis();
+        Self {
+            point,
+            normal,
+            u_axis,
+            v_axis,
+            texture_size: 1.0,
+            mat,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_texture_size(mut self, size: f64) -> Self {
+        // World space size of one tile of the texture
+        self.texture_size = size;
+        self
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        // No hit if the ray is parallel to the plane
+        let denom = self.normal.dot(&ray.dir);
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = self.normal.dot(&(self.point - &ray.origin)) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = ray.at(t);
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(ray, self.normal);
+
+        let offset = rec.p - &self.point;
+        rec.u = (offset.dot(&self.u_axis) / self.texture_size).rem_euclid(1.0);
+        rec.v = (offset.dot(&self.v_axis) / self.texture_size).rem_euclid(1.0);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::universe()
+    }
+}
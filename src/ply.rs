@@ -0,0 +1,481 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    rc::Rc,
+};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+
+use crate::{
+    material::Material,
+    mesh::{TriangleMesh, triangulate},
+    vec3::{Color, Point3, Vec3},
+};
+
+// Stanford PLY importer for ASCII and binary (little and big endian) files. Reads vertex
+// positions, normals (`nx ny nz`), colors (`red green blue`) and UVs (`u v`, `s t` or
+// `texture_u texture_v`), and polygonal faces (`vertex_indices` or `vertex_index`, triangulated
+// by ear clipping). Other elements and properties are skipped. PLY files carry no materials, so
+// the whole mesh uses the given one.
+#[allow(dead_code)]
+pub fn load_ply<P: AsRef<Path>>(path: P, mat: Rc<dyn Material>) -> io::Result<TriangleMesh> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path).map_err(|err| with_path(path, err))?);
+    let header = Header::read(&mut reader).map_err(|err| with_path(path, err))?;
+
+    let mut data = PlyData::default();
+    let result = match header.format {
+        Format::Ascii => {
+            let mut text = String::new();
+            reader
+                .read_to_string(&mut text)
+                .map_err(|err| with_path(path, err))?;
+            let mut values = AsciiValues(text.split_whitespace());
+            data.read(&header, &mut values)
+        }
+        Format::BinaryLittleEndian => {
+            data.read(&header, &mut BinaryValues::<_, LittleEndian>::new(reader))
+        }
+        Format::BinaryBigEndian => {
+            data.read(&header, &mut BinaryValues::<_, BigEndian>::new(reader))
+        }
+    };
+    result.map_err(|err| with_path(path, err))?;
+
+    data.into_mesh(mat)
+}
+
+fn with_path(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(invalid(format!("Unknown PLY type {name}"))),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PropertyType {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    kind: PropertyType,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+impl Header {
+    fn read<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+        let mut line = String::new();
+        let mut next_line = |line: &mut String| -> io::Result<()> {
+            line.clear();
+            if reader.read_line(line)? == 0 {
+                return Err(invalid("PLY header ended early".to_string()));
+            }
+            Ok(())
+        };
+
+        next_line(&mut line)?;
+        if line.trim() != "ply" {
+            return Err(invalid("Not a PLY file".to_string()));
+        }
+
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        loop {
+            next_line(&mut line)?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["end_header"] => break,
+                ["format", name, _version] => {
+                    format = Some(match *name {
+                        "ascii" => Format::Ascii,
+                        "binary_little_endian" => Format::BinaryLittleEndian,
+                        "binary_big_endian" => Format::BinaryBigEndian,
+                        _ => return Err(invalid(format!("Unknown PLY format {name}"))),
+                    });
+                }
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| invalid(format!("Bad element count {count}")))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count, item, name] => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| invalid("PLY property before any element".to_string()))?;
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        kind: PropertyType::List {
+                            count: Scalar::parse(count)?,
+                            item: Scalar::parse(item)?,
+                        },
+                    });
+                }
+                ["property", kind, name] => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| invalid("PLY property before any element".to_string()))?;
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        kind: PropertyType::Scalar(Scalar::parse(kind)?),
+                    });
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => {
+                    return Err(invalid(format!(
+                        "Unexpected PLY header line: {}",
+                        line.trim()
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            format: format.ok_or_else(|| invalid("PLY header has no format".to_string()))?,
+            elements,
+        })
+    }
+}
+
+// Source of property values, in file order
+trait Values {
+    fn next(&mut self, scalar: Scalar) -> io::Result<f64>;
+}
+
+struct AsciiValues<'a>(std::str::SplitWhitespace<'a>);
+
+impl Values for AsciiValues<'_> {
+    fn next(&mut self, _scalar: Scalar) -> io::Result<f64> {
+        let token = self
+            .0
+            .next()
+            .ok_or_else(|| invalid("PLY data ended early".to_string()))?;
+        token
+            .parse()
+            .map_err(|_| invalid(format!("Bad PLY value {token}")))
+    }
+}
+
+struct BinaryValues<R, B> {
+    reader: R,
+    order: std::marker::PhantomData<B>,
+}
+
+impl<R: Read, B: ByteOrder> BinaryValues<R, B> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            order: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<R: Read, B: ByteOrder> Values for BinaryValues<R, B> {
+    fn next(&mut self, scalar: Scalar) -> io::Result<f64> {
+        let reader = &mut self.reader;
+        Ok(match scalar {
+            Scalar::I8 => reader.read_i8()? as f64,
+            Scalar::U8 => reader.read_u8()? as f64,
+            Scalar::I16 => reader.read_i16::<B>()? as f64,
+            Scalar::U16 => reader.read_u16::<B>()? as f64,
+            Scalar::I32 => reader.read_i32::<B>()? as f64,
+            Scalar::U32 => reader.read_u32::<B>()? as f64,
+            Scalar::F32 => reader.read_f32::<B>()? as f64,
+            Scalar::F64 => reader.read_f64::<B>()?,
+        })
+    }
+}
+
+#[derive(Default)]
+struct PlyData {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    colors: Vec<Color>,
+    uvs: Vec<[f64; 2]>,
+    faces: Vec<Vec<u32>>,
+}
+
+impl PlyData {
+    fn read(&mut self, header: &Header, values: &mut dyn Values) -> io::Result<()> {
+        for element in &header.elements {
+            let find = |names: &[&str]| {
+                element
+                    .properties
+                    .iter()
+                    .position(|property| names.contains(&property.name.as_str()))
+            };
+            let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+            let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+            let color = [
+                find(&["red", "r"]),
+                find(&["green", "g"]),
+                find(&["blue", "b"]),
+            ];
+            let uv = [
+                find(&["u", "s", "texture_u", "texture_s"]),
+                find(&["v", "t", "texture_v", "texture_t"]),
+            ];
+            let indices = find(&["vertex_indices", "vertex_index"]);
+            let vertex_count = header
+                .elements
+                .iter()
+                .find(|element| element.name == "vertex")
+                .map_or(0, |element| element.count);
+
+            let mut scalars = vec![0.0; element.properties.len()];
+            for _ in 0..element.count {
+                let mut list = Vec::new();
+                for (index, property) in element.properties.iter().enumerate() {
+                    match property.kind {
+                        PropertyType::Scalar(scalar) => scalars[index] = values.next(scalar)?,
+                        PropertyType::List { count, item } => {
+                            let count = values.next(count)? as usize;
+                            let items = (0..count)
+                                .map(|_| values.next(item))
+                                .collect::<io::Result<Vec<f64>>>()?;
+                            if Some(index) == indices {
+                                list = items;
+                            }
+                        }
+                    }
+                }
+
+                match element.name.as_str() {
+                    "vertex" => {
+                        let get = |index: Option<usize>| index.map(|index| scalars[index]);
+                        let [x, y, z] = position.map(get);
+                        let (Some(x), Some(y), Some(z)) = (x, y, z) else {
+                            return Err(invalid("PLY vertices need x, y and z".to_string()));
+                        };
+                        self.positions.push(Point3::new(x, y, z));
+
+                        if let [Some(x), Some(y), Some(z)] = normal.map(get) {
+                            self.normals.push(Vec3::new(x, y, z));
+                        }
+                        if let [Some(r), Some(g), Some(b)] = color.map(get) {
+                            // Byte colors are gamma encoded, floats are taken as linear
+                            let is_byte = |index: Option<usize>| {
+                                index.is_some_and(|index| {
+                                    matches!(
+                                        element.properties[index].kind,
+                                        PropertyType::Scalar(Scalar::U8)
+                                    )
+                                })
+                            };
+                            let linear = |value: f64, index| {
+                                if is_byte(index) {
+                                    (value / 255.0).powi(2)
+                                } else {
+                                    value
+                                }
+                            };
+                            self.colors.push(Color::new(
+                                linear(r, color[0]),
+                                linear(g, color[1]),
+                                linear(b, color[2]),
+                            ));
+                        }
+                        if let [Some(u), Some(v)] = uv.map(get) {
+                            self.uvs.push([u, v]);
+                        }
+                    }
+                    "face" => {
+                        if indices.is_none() {
+                            return Err(invalid("PLY faces need vertex_indices".to_string()));
+                        }
+                        let face = list
+                            .iter()
+                            .map(|&index| {
+                                if index.fract() != 0.0
+                                    || !(0.0..vertex_count as f64).contains(&index)
+                                {
+                                    return Err(invalid(format!(
+                                        "PLY face index {index} out of range"
+                                    )));
+                                }
+                                Ok(index as u32)
+                            })
+                            .collect::<io::Result<Vec<u32>>>()?;
+                        self.faces.push(face);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_mesh(self, mat: Rc<dyn Material>) -> io::Result<TriangleMesh> {
+        let mut indices = Vec::new();
+        for face in &self.faces {
+            // Indices were checked against the vertex count while reading
+            let polygon: Vec<Point3> = face
+                .iter()
+                .map(|&index| self.positions[index as usize])
+                .collect();
+            for [a, b, c] in triangulate(&polygon) {
+                indices.push([face[a], face[b], face[c]]);
+            }
+        }
+
+        let vertex_count = self.positions.len();
+        let mut mesh = TriangleMesh::new(self.positions, indices, mat);
+        if self.normals.len() == vertex_count {
+            mesh = mesh.with_normals(self.normals);
+        }
+        if self.colors.len() == vertex_count {
+            mesh = mesh.with_colors(self.colors);
+        }
+        if self.uvs.len() == vertex_count {
+            mesh = mesh.with_uvs(self.uvs);
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::material::Lambertian;
+
+    const VERTICES: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+
+    fn header(format: &str, faces: usize) -> String {
+        format!(
+            "ply\nformat {format} 1.0\ncomment test\n\
+             element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             element face {faces}\nproperty list uchar int vertex_indices\nend_header\n"
+        )
+    }
+
+    fn load(name: &str, contents: &[u8]) -> io::Result<TriangleMesh> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let mesh = load_ply(&path, mat);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn loads_ascii_and_binary_files() {
+        let mut ascii = header("ascii", 1);
+        for [x, y, z] in VERTICES {
+            ascii += &format!("{x} {y} {z}\n");
+        }
+        ascii += "4 0 1 2 3\n";
+        let mesh = load("rusttracer_ascii.ply", ascii.as_bytes()).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            // The vertices, then a face of a uchar count and three ints
+            let mut words: Vec<[u8; 4]> = VERTICES
+                .as_flattened()
+                .iter()
+                .map(|value| value.to_be_bytes())
+                .collect();
+            words.extend([0i32, 1, 2].map(i32::to_be_bytes));
+            let mut binary = header(format, 1).into_bytes();
+            for (index, mut word) in words.into_iter().enumerate() {
+                if index == 12 {
+                    binary.push(3);
+                }
+                if !big_endian {
+                    word.reverse();
+                }
+                binary.extend(word);
+            }
+            let mesh = load("rusttracer_binary.ply", &binary).unwrap();
+            assert_eq!(mesh.triangle_count(), 1, "{format}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_face_indices() {
+        for face in ["3 0 1 4", "3 0 -1 2", "3 0 1.5 2"] {
+            let mut ascii = header("ascii", 1);
+            ascii += "0 0 0\n1 0 0\n1 1 0\n0 1 0\n";
+            ascii += face;
+            let err = load("rusttracer_bad_index.ply", ascii.as_bytes())
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{face}");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let err = load("rusttracer_truncated.ply", header("ascii", 1).as_bytes())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(load("rusttracer_not.ply", b"solid cube\n").is_err());
+
+        // Errors name the file, including ones reading the ASCII body
+        let mut not_utf8 = header("ascii", 0).into_bytes();
+        not_utf8.extend(b"0 0 \xff\n");
+        let err = load("rusttracer_not_utf8.ply", &not_utf8).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("rusttracer_not_utf8.ply"), "{err}");
+    }
+}
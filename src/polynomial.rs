@@ -0,0 +1,246 @@
+// Real roots of low degree polynomials, for intersecting rays with algebraic surfaces.
+//
+// The polynomials are monic (leading coefficient one) and the roots come back unordered in a
+// fixed size array, padded with NaN where there are fewer real roots. NaN compares false with
+// everything, so callers can run each entry through `Interval::surrounds` without counting.
+
+use std::f64::consts::TAU;
+
+const EPSILON: f64 = 1e-12;
+
+// How much larger than the others one root has to be for the cubic and quartic to be solved in
+// reverse, see `has_outlying_root`
+const ROOT_SPREAD: f64 = 1e4;
+
+#[allow(dead_code)]
+pub fn solve_quadratic(b: f64, c: f64) -> [f64; 2] {
+    // x^2 + b x + c = 0
+    let discriminant = b * b - 4.0 * c;
+    if discriminant < 0.0 {
+        return [f64::NAN; 2];
+    }
+
+    // Avoids the cancellation of -b + sqrt(..) when b is large, by getting the smaller root
+    // from the product of the roots (c) instead
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    if q == 0.0 {
+        return [0.0, 0.0];
+    }
+    [q, c / q]
+}
+
+#[allow(dead_code)]
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> [f64; 3] {
+    // x^3 + a x^2 + b x + c = 0, which always has at least one real root
+    if !has_outlying_root(&[a, b, c]) {
+        return cubic_roots(a, b, c);
+    }
+
+    // The outlier comes back as the reciprocal of a tiny root, and only as precise as that was
+    // in absolute terms, so polish the roots on the original polynomial
+    let mut roots = cubic_roots(b / c, a / c, 1.0 / c).map(f64::recip);
+    for root in roots.iter_mut().filter(|root| !root.is_nan()) {
+        for _ in 0..2 {
+            let x = *root;
+            let f = ((x + a) * x + b) * x + c;
+            let df = (3.0 * x + 2.0 * a) * x + b;
+            if df.abs() < EPSILON {
+                break;
+            }
+            *root = x - f / df;
+        }
+    }
+    roots
+}
+
+fn cubic_roots(a: f64, b: f64, c: f64) -> [f64; 3] {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let shift = a / 3.0;
+
+    let q3 = q * q * q;
+    if r * r < q3 {
+        // Three real roots, from the trigonometric form
+        let angle = (r / q3.sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let scale = -2.0 * q.sqrt();
+        let third = TAU / 3.0;
+        return [
+            scale * angle.cos() - shift,
+            scale * (angle + third).cos() - shift,
+            scale * (angle - third).cos() - shift,
+        ];
+    }
+
+    // One real root (Cardano), and a complex pair. When their imaginary part is down to rounding
+    // error the pair is really a repeated real root, which rounding pushed out of the branch above.
+    let big_a = -(r.abs() + (r * r - q3).sqrt()).cbrt().copysign(r);
+    let big_b = if big_a == 0.0 { 0.0 } else { q / big_a };
+    if (big_a - big_b).abs() <= 1e-6 * big_a.abs() {
+        let double = -0.5 * (big_a + big_b) - shift;
+        return [big_a + big_b - shift, double, double];
+    }
+    [big_a + big_b - shift, f64::NAN, f64::NAN]
+}
+
+#[allow(dead_code)]
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> [f64; 4] {
+    // x^4 + a x^3 + b x^2 + c x + d = 0
+    let mut roots = if has_outlying_root(&[a, b, c, d]) {
+        quartic_roots(c / d, b / d, a / d, 1.0 / d).map(f64::recip)
+    } else {
+        quartic_roots(a, b, c, d)
+    };
+
+    // The closed form loses precision when roots are close together or far apart in size, so
+    // polish each root with a couple of Newton steps on the original polynomial
+    for root in roots.iter_mut().filter(|root| !root.is_nan()) {
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((x + a) * x + b) * x + c) * x + d;
+            let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+            if df.abs() < EPSILON {
+                break;
+            }
+            *root = x - f / df;
+        }
+    }
+    roots
+}
+
+fn quartic_roots(a: f64, b: f64, c: f64, d: f64) -> [f64; 4] {
+    // Ferrari's method: substituting x = y - a/4 gives y^4 + p y^2 + q y + r = 0, which a root z
+    // of the resolvent cubic splits into two quadratics
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = c - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * c / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+    let shift = a / 4.0;
+
+    let mut roots = [f64::NAN; 4];
+    if q.abs() < EPSILON {
+        // Biquadratic: y^4 + p y^2 + r = 0 is a quadratic in y^2
+        for (i, y2) in solve_quadratic(p, r).into_iter().enumerate() {
+            if y2 >= 0.0 {
+                let y = y2.sqrt();
+                roots[2 * i] = y - shift;
+                roots[2 * i + 1] = -y - shift;
+            }
+        }
+    } else {
+        // z^3 - p/2 z^2 - r z + (r p/2 - q^2/8) = 0. Its largest root keeps the square roots
+        // below as far from cancelling as possible.
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)
+            .into_iter()
+            .filter(|z| !z.is_nan())
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -EPSILON || v < -EPSILON {
+            return roots;
+        }
+        let u = u.max(0.0).sqrt();
+        let v = v.max(0.0).sqrt().copysign(q);
+
+        let [y0, y1] = solve_quadratic(v, z - u);
+        let [y2, y3] = solve_quadratic(-v, z + u);
+        roots = [y0 - shift, y1 - shift, y2 - shift, y3 - shift];
+    }
+    roots
+}
+
+fn has_outlying_root(coefficients: &[f64]) -> bool {
+    // A polynomial that was made monic by dividing through a tiny leading coefficient has one
+    // root near -a, far larger than the others, which the closed forms then lose to cancellation.
+    // Reversing the coefficients gives the polynomial of the reciprocal roots, where the outlier
+    // is the smallest root instead and the rest keep their precision. The others' size is
+    // estimated from the lower coefficients, relative to a.
+    let [a, rest @ ..] = coefficients else {
+        return false;
+    };
+    let constant = rest.last().copied().unwrap_or(0.0);
+    constant != 0.0
+        && rest.iter().enumerate().all(|(i, coefficient)| {
+            let power = i as i32 + 1;
+            coefficient.abs() * ROOT_SPREAD.powi(power) < a.abs().powi(power + 1)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: &[f64], expected: &[f64], tolerance: f64) {
+        // Every real root is one of the expected ones and every expected one is found, so
+        // repeated roots may come back once or several times
+        let close = |x: f64, y: f64| (x - y).abs() <= tolerance * y.abs().max(1.0);
+        let real: Vec<f64> = roots.iter().copied().filter(|x| !x.is_nan()).collect();
+        for &root in &real {
+            assert!(
+                expected.iter().any(|&y| close(root, y)),
+                "{root} isn't one of {expected:?} (got {roots:?})"
+            );
+        }
+        for &y in expected {
+            assert!(
+                real.iter().any(|&root| close(root, y)),
+                "{y} is missing (got {roots:?})"
+            );
+        }
+    }
+
+    #[test]
+    fn quadratic_roots() {
+        assert_roots(&solve_quadratic(-4.0, 3.0), &[1.0, 3.0], 1e-12);
+        assert_roots(&solve_quadratic(-4.0, 4.0), &[2.0], 1e-12);
+        assert_roots(&solve_quadratic(0.0, 0.0), &[0.0], 1e-12);
+        assert_roots(&solve_quadratic(0.0, 1.0), &[], 0.0);
+        // (x + 1e10) (x - 1), as left by dividing through a tiny leading coefficient. The small
+        // root would cancel to nothing in the textbook formula.
+        assert_roots(&solve_quadratic(1e10 - 1.0, -1e10), &[1.0, -1e10], 1e-12);
+    }
+
+    #[test]
+    fn cubic_roots() {
+        assert_roots(&solve_cubic(-6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-12);
+        // (x - 1)^2 (x - 2) and (x - 1)^3
+        assert_roots(&solve_cubic(-4.0, 5.0, -2.0), &[1.0, 2.0], 1e-7);
+        assert_roots(&solve_cubic(-3.0, 3.0, -1.0), &[1.0], 1e-12);
+        // (x + 1) (x^2 - x + 2), whose other roots are complex
+        let roots = solve_cubic(0.0, 1.0, 2.0);
+        assert_roots(&roots, &[-1.0], 1e-12);
+        assert_eq!(roots.iter().filter(|x| x.is_nan()).count(), 2);
+        // (x + 1e9) (x - 1) (x - 2), a near-degenerate leading coefficient again
+        assert_roots(
+            &solve_cubic(1e9 - 3.0, 2.0 - 3e9, 2e9),
+            &[1.0, 2.0, -1e9],
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn quartic_roots() {
+        assert_roots(
+            &solve_quartic(-10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+            1e-12,
+        );
+        // (x - 1)^2 (x - 3)^2, and the biquadratic (x^2 - 1) (x^2 - 4)
+        assert_roots(&solve_quartic(-8.0, 22.0, -24.0, 9.0), &[1.0, 3.0], 1e-7);
+        assert_roots(
+            &solve_quartic(0.0, -5.0, 0.0, 4.0),
+            &[-2.0, -1.0, 1.0, 2.0],
+            1e-12,
+        );
+        // (x^2 + 1) (x - 1) (x - 2) has two real roots, x^4 + 1 and (x^2 + 1) (x^2 + 4) none
+        assert_roots(&solve_quartic(-3.0, 3.0, -3.0, 2.0), &[1.0, 2.0], 1e-12);
+        assert_roots(&solve_quartic(0.0, 0.0, 0.0, 1.0), &[], 0.0);
+        assert_roots(&solve_quartic(0.0, 5.0, 0.0, 4.0), &[], 0.0);
+        // (x + 1e6) (x - 1) (x - 2) (x - 3)
+        assert_roots(
+            &solve_quartic(1e6 - 6.0, 11.0 - 6e6, 11e6 - 6.0, -6e6),
+            &[1.0, 2.0, 3.0, -1e6],
+            1e-9,
+        );
+    }
+}
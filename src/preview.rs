@@ -0,0 +1,120 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    exporter::color_to_bytes,
+    film::Film,
+    progress::{Progress, ProgressObserver, status_line},
+    vec3::Color,
+};
+
+// Draws a downscaled live preview of the render on stderr using 24-bit ANSI colors. Each
+// character cell shows two pixels stacked vertically: the upper half block glyph takes the top
+// pixel as its foreground color and the bottom pixel as its background. The preview is redrawn
+// in place as tiles finish (throttled) and at the end of every pass, with a progress line below.
+pub struct TerminalPreview {
+    columns: usize,
+    refresh: Duration,
+    last_draw: Option<Instant>,
+    drawn_lines: usize,
+}
+
+impl TerminalPreview {
+    #[allow(dead_code)]
+    pub fn new(columns: usize, refresh: Duration) -> Self {
+        Self {
+            columns: columns.max(1),
+            refresh,
+            last_draw: None,
+            drawn_lines: 0,
+        }
+    }
+
+    fn cell_color(film: &Film, x0: i32, x1: i32, y0: i32, y1: i32) -> [u8; 3] {
+        // Box filter over the film pixels covered by one preview pixel
+        let mut sum = Color::default();
+        let mut count = 0;
+        for y in y0..y1.max(y0 + 1).min(film.height()) {
+            for x in x0..x1.max(x0 + 1).min(film.width()) {
+                sum += &film.pixel(x, y);
+                count += 1;
+            }
+        }
+        color_to_bytes(if count > 0 { sum / count as f64 } else { sum })
+    }
+
+    fn draw(&mut self, progress: &Progress, film: &Film) {
+        let columns = self.columns.min(film.width() as usize).max(1);
+        let rows =
+            ((columns as f64 * film.height() as f64 / film.width() as f64).round() as usize).max(1);
+
+        // Map preview pixels back to ranges of film pixels
+        let film_x = |c: usize| (c * film.width() as usize / columns) as i32;
+        let film_y = |r: usize| (r * film.height() as usize / rows) as i32;
+
+        let mut out = String::new();
+
+        // Move back up over the previous frame so it's overwritten in place
+        if self.drawn_lines > 0 {
+            let _ = write!(out, "\r\x1b[{}A", self.drawn_lines);
+        }
+
+        for row in (0..rows).step_by(2) {
+            for column in 0..columns {
+                let (x0, x1) = (film_x(column), film_x(column + 1));
+                let [tr, tg, tb] = Self::cell_color(film, x0, x1, film_y(row), film_y(row + 1));
+                if row + 1 < rows {
+                    let [br, bg, bb] =
+                        Self::cell_color(film, x0, x1, film_y(row + 1), film_y(row + 2));
+                    let _ = write!(
+                        out,
+                        "\x1b[38;2;{tr};{tg};{tb}m\x1b[48;2;{br};{bg};{bb}m\u{2580}"
+                    );
+                } else {
+                    let _ = write!(out, "\x1b[38;2;{tr};{tg};{tb}m\x1b[49m\u{2580}");
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out.push_str("\x1b[2K");
+        out.push_str(&status_line(progress, 40));
+
+        self.drawn_lines = rows.div_ceil(2);
+
+        let mut stderr = io::stderr().lock();
+        let _ = stderr.write_all(out.as_bytes());
+        let _ = stderr.flush();
+    }
+}
+
+impl Default for TerminalPreview {
+    fn default() -> Self {
+        Self::new(80, Duration::from_millis(500))
+    }
+}
+
+impl ProgressObserver for TerminalPreview {
+    fn on_progress(&mut self, _progress: &Progress) -> bool {
+        true
+    }
+
+    fn on_film_update(&mut self, progress: &Progress, film: &Film) {
+        // Redraw when a pass completes, otherwise at most once per refresh period
+        let pass_done = progress.completed_tiles == progress.total_tiles;
+        if pass_done
+            || self
+                .last_draw
+                .is_none_or(|last| last.elapsed() >= self.refresh)
+        {
+            self.last_draw = Some(Instant::now());
+            self.draw(progress, film);
+        }
+    }
+
+    fn on_finish(&mut self, _progress: &Progress) {
+        eprintln!();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    io::{self, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::film::Film;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    pub completed_pixels: u64, // pixels done in the current pass
+    pub total_pixels: u64,
+    pub completed_tiles: u64, // tiles done in the current pass
+    pub total_tiles: u64,
+    pub passes_done: u32,
+    pub samples_done: u64,
+    pub total_samples: u64, // zero when rendering to a time budget
+    pub elapsed: Duration,
+    pub time_budget: Option<Duration>,
+}
+
+impl Progress {
+    #[allow(dead_code)]
+    pub fn fraction(&self) -> f64 {
+        // Fraction of the render completed, in [0,1]
+        if let Some(budget) = self.time_budget {
+            return (self.elapsed.as_secs_f64() / budget.as_secs_f64()).min(1.0);
+        }
+        if self.total_samples == 0 {
+            return 0.0;
+        }
+        (self.samples_done as f64 / self.total_samples as f64).min(1.0)
+    }
+
+    #[allow(dead_code)]
+    pub fn eta(&self) -> Option<Duration> {
+        // Estimated time remaining, extrapolated from the average sample rate so far. Returns
+        // None until there's enough work done to make a guess.
+        if let Some(budget) = self.time_budget {
+            return Some(budget.saturating_sub(self.elapsed));
+        }
+        if self.samples_done == 0 {
+            return None;
+        }
+        let remaining = self.total_samples.saturating_sub(self.samples_done);
+        let secs_per_sample = self.elapsed.as_secs_f64() / self.samples_done as f64;
+        Some(Duration::from_secs_f64(secs_per_sample * remaining as f64))
+    }
+}
+
+// Shared flag for stopping a render from elsewhere (another thread, a signal handler, ...). The
+// render stops at the next pixel and still exports what it has accumulated.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    #[allow(dead_code)]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[allow(dead_code)]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub trait ProgressObserver {
+    fn on_start(&mut self, _progress: &Progress) {}
+
+    // Called as each tile completes. Returning false cancels the render.
+    fn on_progress(&mut self, progress: &Progress) -> bool;
+
+    // Called after each tile with the film as rendered so far, for observers that show the image
+    fn on_film_update(&mut self, _progress: &Progress, _film: &Film) {}
+
+    fn on_finish(&mut self, _progress: &Progress) {}
+}
+
+// Draws a progress bar with an ETA on stderr
+pub struct TerminalProgress {
+    bar_width: usize,
+    refresh: Duration,
+    last_draw: Option<Instant>,
+}
+
+impl TerminalProgress {
+    #[allow(dead_code)]
+    pub fn new(bar_width: usize, refresh: Duration) -> Self {
+        Self {
+            bar_width,
+            refresh,
+            last_draw: None,
+        }
+    }
+
+    fn draw(&self, progress: &Progress) {
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}", status_line(progress, self.bar_width));
+        let _ = stderr.flush();
+    }
+}
+
+pub fn status_line(progress: &Progress, bar_width: usize) -> String {
+    // Single line progress bar with pass, tile and timing details
+    let fraction = progress.fraction();
+    let filled = (fraction * bar_width as f64) as usize;
+    let eta = match progress.eta() {
+        Some(eta) => format_duration(eta),
+        None => "--:--".to_string(),
+    };
+
+    format!(
+        "[{}{}] {:5.1}%  {} passes done  {}/{} tiles  {}/{} px  elapsed {}  eta {}  ",
+        "#".repeat(filled),
+        ".".repeat(bar_width - filled),
+        fraction * 100.0,
+        progress.passes_done,
+        progress.completed_tiles,
+        progress.total_tiles,
+        progress.completed_pixels,
+        progress.total_pixels,
+        format_duration(progress.elapsed),
+        eta,
+    )
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new(40, Duration::from_millis(100))
+    }
+}
+
+impl ProgressObserver for TerminalProgress {
+    fn on_start(&mut self, progress: &Progress) {
+        self.last_draw = Some(Instant::now());
+        self.draw(progress);
+    }
+
+    fn on_progress(&mut self, progress: &Progress) -> bool {
+        // Throttle redraws so the terminal isn't the bottleneck
+        if self
+            .last_draw
+            .is_none_or(|last| last.elapsed() >= self.refresh)
+        {
+            self.last_draw = Some(Instant::now());
+            self.draw(progress);
+        }
+        true
+    }
+
+    fn on_finish(&mut self, progress: &Progress) {
+        self.draw(progress);
+        eprintln!();
+    }
+}
+
+// Ignores all progress events, for batch jobs and embedding
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SilentProgress;
+
+impl ProgressObserver for SilentProgress {
+    fn on_progress(&mut self, _progress: &Progress) -> bool {
+        true
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -8,7 +8,9 @@ use std::{
     str::FromStr,
 };
 
-use stb_image::stb_image::stbi_loadf;
+use stb_image::stb_image::{
+    stbi_is_hdr, stbi_is_hdr_from_memory, stbi_loadf, stbi_loadf_from_memory,
+};
 
 use crate::vec3::Color;
 
@@ -78,6 +80,7 @@ pub struct RTWImage {
     image_width: i32,            // Loaded image width
     image_height: i32,           // Loaded image height
     bytes_per_scanline: i32,
+    hdr: bool, // Float source (Radiance .hdr), loaded as is rather than linearized
 }
 
 impl RTWImage {
@@ -143,8 +146,43 @@ impl RTWImage {
         if ptr.is_null() {
             return false;
         }
-        let len = self.image_width * self.image_height * n;
+        // The data has the requested channel count, whatever the file had
+        let len = self.image_width * self.image_height * Self::BYTES_PER_PIXEL;
+        self.fdata = Some(CBuffer::from_ptr(ptr, len as usize));
+        self.hdr = unsafe { stbi_is_hdr(path.as_ptr()) } != 0;
+
+        self.bytes_per_scanline = self.image_width * Self::BYTES_PER_PIXEL;
+        self.convert_to_bytes();
+
+        true
+    }
+
+    #[allow(dead_code)]
+    pub fn load_from_memory(&mut self, bytes: &[u8]) -> bool {
+        // Like `load`, for an encoded image (PNG, JPEG, ...) that's already in memory
+        let mut n = Self::BYTES_PER_PIXEL;
+        let Ok(byte_len) = i32::try_from(bytes.len()) else {
+            return false;
+        };
+
+        let ptr = unsafe {
+            stbi_loadf_from_memory(
+                bytes.as_ptr(),
+                byte_len,
+                &mut self.image_width,
+                &mut self.image_height,
+                &mut n,
+                Self::BYTES_PER_PIXEL,
+            )
+        };
+
+        if ptr.is_null() {
+            return false;
+        }
+        // The data has the requested channel count, whatever the file had
+        let len = self.image_width * self.image_height * Self::BYTES_PER_PIXEL;
         self.fdata = Some(CBuffer::from_ptr(ptr, len as usize));
+        self.hdr = unsafe { stbi_is_hdr_from_memory(bytes.as_ptr(), byte_len) } != 0;
 
         self.bytes_per_scanline = self.image_width * Self::BYTES_PER_PIXEL;
         self.convert_to_bytes();
@@ -183,6 +221,29 @@ impl RTWImage {
             .expect("Slice length not met")
     }
 
+    #[allow(dead_code)]
+    pub fn is_hdr(&self) -> bool {
+        // Whether the file held floating point data, which `linear_pixel_at` returns unchanged
+        self.hdr
+    }
+
+    #[allow(dead_code)]
+    pub fn linear_pixel_at(&self, x: i32, y: i32) -> [f32; 3] {
+        // The floating point RGB values of the pixel at x,y, clamped to the image like
+        // `pixel_at`. Loading linearizes 8 and 16-bit images with a gamma of 2.2, HDR images
+        // are already linear.
+        let Some(fdata) = self.fdata.as_ref() else {
+            return [0.0; 3];
+        };
+
+        let x = Self::clamp(x, 0, self.image_width);
+        let y = Self::clamp(y, 0, self.image_height);
+        let offset = ((y * self.image_width + x) * Self::BYTES_PER_PIXEL) as usize;
+        fdata.as_slice()[offset..offset + 3]
+            .try_into()
+            .expect("Slice length not met")
+    }
+
     fn convert_to_bytes(&mut self) {
         let fdata = self.fdata.as_mut().unwrap();
 
@@ -224,3 +285,34 @@ impl RTWImage {
         return (256.0 * value) as u8;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1x1 RGB PNG of gray level 51 (0.2)
+    const TINY_PNG: [u8; 69] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90,
+        0x77, 0x53, 0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x30,
+        0x36, 0x36, 0x06, 0x00, 0x01, 0x36, 0x00, 0x9a, 0x79, 0xde, 0xa0, 0xa5, 0x00, 0x00, 0x00,
+        0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn tells_hdr_from_8_bit_images_in_memory() {
+        let mut image = RTWImage::default();
+        assert!(image.load_from_memory(&TINY_PNG));
+        assert!(!image.is_hdr());
+        let [r, g, b] = image.linear_pixel_at(0, 0);
+        assert!((r - 0.2f32.powf(2.2)).abs() < 1e-3 && r == g && g == b);
+
+        // Also 1x1, so the decoded data is shorter than the file's signature
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 1\n".to_vec();
+        hdr.extend([128, 128, 128, 129]);
+        let mut image = RTWImage::default();
+        assert!(image.load_from_memory(&hdr));
+        assert!(image.is_hdr());
+        assert_eq!(image.linear_pixel_at(0, 0), [1.0; 3]);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// A signed distance function: the distance from a point to the nearest surface, negative inside.
+// Any `Fn(&Point3) -> f64` closure is one, so shapes and the operators below combine freely:
+//
+//     let sdf = sdf::smooth_union(sdf::sphere(c, 1.0), sdf::capsule(a, b, 0.3), 0.2);
+//
+// The result is rendered with `SdfObject`.
+pub trait Sdf {
+    fn distance(&self, p: &Point3) -> f64;
+}
+
+impl<F: Fn(&Point3) -> f64> Sdf for F {
+    fn distance(&self, p: &Point3) -> f64 {
+        self(p)
+    }
+}
+
+// Sphere tracing stops this close to the surface
+const SURFACE_DISTANCE: f64 = 1e-4;
+const MAX_STEPS: usize = 512;
+// Offset of the samples for the gradient
+const NORMAL_OFFSET: f64 = 1e-5;
+
+// Renders an `Sdf` by sphere tracing: stepping along the ray by the distance to the nearest
+// surface, which can never overshoot it, until that distance is negligible. Marching is limited
+// to the bounding box given, which must contain the whole surface (it's also what the BVH sees).
+//
+// Normals come from the distance's gradient. Texture coordinates map the normal's direction like
+// a sphere's, though solid textures suit these shapes better.
+pub struct SdfObject {
+    sdf: Box<dyn Sdf>,
+    bbox: AABB,
+    mat: Rc<dyn Material>,
+    step_scale: f64,
+}
+
+impl SdfObject {
+    #[allow(dead_code)]
+    pub fn new(sdf: impl Sdf + 'static, bbox: AABB, mat: Rc<dyn Material>) -> Self {
+        Self {
+            sdf: Box::new(sdf),
+            bbox,
+            mat,
+            step_scale: 1.0,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_step_scale(mut self, scale: f64) -> Self {
+        // Shortens every step by this factor (below 1), for functions that overestimate the
+        // distance, like strongly deformed ones; slower but no more holes in the surface
+        self.step_scale = scale;
+        self
+    }
+
+    fn gradient(&self, p: &Point3) -> Vec3 {
+        // Finite differences towards the corners of a tetrahedron, four evaluations
+        let h = NORMAL_OFFSET;
+        let mut gradient = Vec3::default();
+        for k in [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ] {
+            gradient += &(k * self.sdf.distance(&(*p + &(k * h))));
+        }
+        gradient
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        let Some(span) = self.bbox.clip(ray, ray_t) else {
+            return false;
+        };
+        let dir_len = ray.dir.len();
+        let mut t = span.min;
+
+        // Rays starting inside the shape (through glass, say) march towards the surface from
+        // the other side. A ray leaving the surface it starts on (the hit it bounced off) has to
+        // get clear of it first, or it would hit the same point again.
+        let start = ray.at(t);
+        let start_distance = self.sdf.distance(&start);
+        let mut leaving = start_distance.abs() < SURFACE_DISTANCE;
+        let side = if leaving {
+            self.gradient(&start).dot(&ray.dir).signum()
+        } else {
+            start_distance.signum()
+        };
+
+        // Only reaching the surface counts as a hit; running out of steps (a ray grazing an edge,
+        // or a small step scale) leaves the ray somewhere in mid-air
+        let mut converged = false;
+        for _ in 0..MAX_STEPS {
+            let distance = side * self.sdf.distance(&ray.at(t));
+            if leaving {
+                leaving = distance < SURFACE_DISTANCE;
+            } else if distance < SURFACE_DISTANCE {
+                converged = true;
+                break;
+            }
+
+            t += distance.max(SURFACE_DISTANCE) * self.step_scale / dir_len;
+            if t > span.max {
+                return false;
+            }
+        }
+        if !converged || !span.contains(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = ray.at(t);
+        rec.mat = Some(self.mat.clone());
+        let normal = self.gradient(&rec.p).unit_vector();
+        rec.set_face_normal(ray, normal);
+
+        // Same mapping as `Sphere`, applied to the normal
+        rec.u = (f64::atan2(-normal.z(), normal.x()) + PI) / (2.0 * PI);
+        rec.v = (-normal.y()).clamp(-1.0, 1.0).acos() / PI;
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+// Shapes
+
+#[allow(dead_code)]
+pub fn sphere(center: Point3, radius: f64) -> impl Sdf {
+    move |p: &Point3| (*p - &center).len() - radius
+}
+
+#[allow(dead_code)]
+pub fn rounded_box(center: Point3, half_size: Vec3, radius: f64) -> impl Sdf {
+    // Box of the given half extents around `center`, its edges rounded off by `radius`
+    move |p: &Point3| {
+        let q = Vec3::new(
+            (p.x() - center.x()).abs() - half_size.x() + radius,
+            (p.y() - center.y()).abs() - half_size.y() + radius,
+            (p.z() - center.z()).abs() - half_size.z() + radius,
+        );
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).len();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - radius
+    }
+}
+
+#[allow(dead_code)]
+pub fn capsule(a: Point3, b: Point3, radius: f64) -> impl Sdf {
+    // Points within `radius` of the segment from `a` to `b`
+    move |p: &Point3| {
+        let pa = *p - &a;
+        let ba = b - &a;
+        let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+        (pa - &(ba * h)).len() - radius
+    }
+}
+
+#[allow(dead_code)]
+pub fn torus(center: Point3, major_radius: f64, minor_radius: f64) -> impl Sdf {
+    // Torus around the y axis through `center`
+    move |p: &Point3| {
+        let q = *p - &center;
+        let ring = (q.x() * q.x() + q.z() * q.z()).sqrt() - major_radius;
+        (ring * ring + q.y() * q.y()).sqrt() - minor_radius
+    }
+}
+
+// Operators
+
+#[allow(dead_code)]
+pub fn union(a: impl Sdf, b: impl Sdf) -> impl Sdf {
+    move |p: &Point3| a.distance(p).min(b.distance(p))
+}
+
+#[allow(dead_code)]
+pub fn intersection(a: impl Sdf, b: impl Sdf) -> impl Sdf {
+    move |p: &Point3| a.distance(p).max(b.distance(p))
+}
+
+#[allow(dead_code)]
+pub fn subtraction(a: impl Sdf, b: impl Sdf) -> impl Sdf {
+    // `a` with `b` cut out of it
+    move |p: &Point3| a.distance(p).max(-b.distance(p))
+}
+
+// The smooth operators blend the surfaces together over about `k` units where they meet,
+// instead of leaving a sharp crease
+
+#[allow(dead_code)]
+pub fn smooth_union(a: impl Sdf, b: impl Sdf, k: f64) -> impl Sdf {
+    move |p: &Point3| {
+        let (da, db) = (a.distance(p), b.distance(p));
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h - k * h * (1.0 - h)
+    }
+}
+
+#[allow(dead_code)]
+pub fn smooth_subtraction(a: impl Sdf, b: impl Sdf, k: f64) -> impl Sdf {
+    move |p: &Point3| {
+        let (da, db) = (a.distance(p), -b.distance(p));
+        let h = (0.5 - 0.5 * (da - db) / k).clamp(0.0, 1.0);
+        da + (db - da) * h + k * h * (1.0 - h)
+    }
+}
+
+#[allow(dead_code)]
+pub fn smooth_intersection(a: impl Sdf, b: impl Sdf, k: f64) -> impl Sdf {
+    move |p: &Point3| {
+        let (da, db) = (a.distance(p), b.distance(p));
+        let h = (0.5 - 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h + k * h * (1.0 - h)
+    }
+}
+
+#[allow(dead_code)]
+pub fn translate(sdf: impl Sdf, offset: Vec3) -> impl Sdf {
+    move |p: &Point3| sdf.distance(&(*p - &offset))
+}
+
+#[allow(dead_code)]
+pub fn repeat(sdf: impl Sdf, period: Vec3) -> impl Sdf {
+    // Infinitely many copies, `period` apart along each axis (0 leaves an axis alone). The
+    // shape should fit in one cell around the origin, and the `SdfObject`'s box picks how many
+    // copies show.
+    let wrap = |x: f64, period: f64| {
+        if period > 0.0 {
+            x - period * (x / period).round()
+        } else {
+            x
+        }
+    };
+    move |p: &Point3| {
+        let q = Point3::new(
+            wrap(p.x(), period.x()),
+            wrap(p.y(), period.y()),
+            wrap(p.z(), period.z()),
+        );
+        sdf.distance(&q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, vec3::Color};
+
+    fn hit(object: &SdfObject, ray: &Ray) -> Option<HitRecord> {
+        let mut rec = HitRecord::default();
+        object
+            .hit(ray, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    #[test]
+    fn only_reports_hits_on_the_surface() {
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let bbox = AABB::from_points(
+            Point3::new(-10.0, -10.0, -10.0),
+            Point3::new(10.0, 10.0, 10.0),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+        let object = SdfObject::new(sphere(Point3::default(), 1.0), bbox, mat.clone());
+        let rec = hit(&object, &ray).unwrap();
+        assert!((rec.t - 9.0).abs() < SURFACE_DISTANCE);
+        assert!(rec.front_face);
+
+        // Steps this short run out long before reaching the sphere
+        let object =
+            SdfObject::new(sphere(Point3::default(), 1.0), bbox, mat).with_step_scale(0.01);
+        assert!(hit(&object, &ray).is_none());
+    }
+}
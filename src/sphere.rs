@@ -6,6 +6,7 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats,
     vec3::{Point3, Vec3},
 };
 
@@ -70,6 +71,8 @@ impl Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
         let current_center = self.center.at(ray.time);
         let oc = current_center - &ray.origin;
         let a = ray.dir.len_squared();
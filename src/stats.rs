@@ -0,0 +1,243 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// Per-ray counters, bumped from the hot paths (camera, BVH, primitives) and collected by the
+// camera at the end of each render. They're plain thread-local cells rather than shared atomics,
+// so counting costs an add and a store per test; renders run on the thread that collects them.
+thread_local! {
+    static PRIMARY_RAYS: Cell<u64> = const { Cell::new(0) };
+    static SECONDARY_RAYS: Cell<u64> = const { Cell::new(0) };
+    static BVH_NODE_TESTS: Cell<u64> = const { Cell::new(0) };
+    static PRIMITIVE_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+// BVH build figures, recorded once per hierarchy
+static BVH_BUILD_NANOS: AtomicU64 = AtomicU64::new(0);
+static BVH_NODES: AtomicU64 = AtomicU64::new(0);
+static BVH_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// Heap usage, tracked by `CountingAllocator`
+static HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+#[inline(always)]
+pub fn record_primary_ray() {
+    PRIMARY_RAYS.set(PRIMARY_RAYS.get() + 1);
+}
+
+#[inline(always)]
+pub fn record_secondary_ray() {
+    SECONDARY_RAYS.set(SECONDARY_RAYS.get() + 1);
+}
+
+#[inline(always)]
+pub fn record_bvh_node_test() {
+    BVH_NODE_TESTS.set(BVH_NODE_TESTS.get() + 1);
+}
+
+#[inline(always)]
+pub fn record_primitive_test() {
+    PRIMITIVE_TESTS.set(PRIMITIVE_TESTS.get() + 1);
+}
+
+pub fn record_bvh_build(duration: Duration, nodes: u64, bytes: u64) {
+    BVH_BUILD_NANOS.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    BVH_NODES.fetch_add(nodes, Ordering::Relaxed);
+    BVH_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn reset_ray_counters() {
+    // Clears the per-render counters. BVH build time is kept, as the hierarchy is usually built
+    // before the render starts.
+    for counter in [
+        &PRIMARY_RAYS,
+        &SECONDARY_RAYS,
+        &BVH_NODE_TESTS,
+        &PRIMITIVE_TESTS,
+    ] {
+        counter.set(0);
+    }
+}
+
+// Wraps the system allocator to keep track of the current and peak heap usage. Registered as the
+// global allocator in main.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = HEAP_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_HEAP_BYTES.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        HEAP_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            HEAP_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            let current = HEAP_BYTES.fetch_add(new_size, Ordering::Relaxed) + new_size;
+            PEAK_HEAP_BYTES.fetch_max(current, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[allow(dead_code)]
+pub fn heap_bytes() -> usize {
+    HEAP_BYTES.load(Ordering::Relaxed)
+}
+
+#[allow(dead_code)]
+pub fn peak_heap_bytes() -> usize {
+    PEAK_HEAP_BYTES.load(Ordering::Relaxed)
+}
+
+#[allow(dead_code)]
+pub fn reset_peak_heap() {
+    // Starts measuring the peak again from the current usage
+    PEAK_HEAP_BYTES.store(HEAP_BYTES.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    pub primary_rays: u64,
+    pub secondary_rays: u64,
+    pub bvh_node_tests: u64,
+    pub primitive_tests: u64,
+    pub bvh_build_time: Duration,
+    pub bvh_nodes: u64,
+    pub bvh_bytes: u64,
+    pub render_time: Duration,
+    pub export_time: Duration,
+}
+
+impl RenderStats {
+    pub fn collect(render_time: Duration, export_time: Duration) -> Self {
+        // Gathers this thread's counters into a summary. The accumulated BVH build figures are
+        // consumed so the next render only reports the hierarchies built for it.
+        Self {
+            primary_rays: PRIMARY_RAYS.get(),
+            secondary_rays: SECONDARY_RAYS.get(),
+            bvh_node_tests: BVH_NODE_TESTS.get(),
+            primitive_tests: PRIMITIVE_TESTS.get(),
+            bvh_build_time: Duration::from_nanos(BVH_BUILD_NANOS.swap(0, Ordering::Relaxed)),
+            bvh_nodes: BVH_NODES.swap(0, Ordering::Relaxed),
+            bvh_bytes: BVH_BYTES.swap(0, Ordering::Relaxed),
+            render_time,
+            export_time,
+        }
+    }
+
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays + self.secondary_rays
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let secs = self.render_time.as_secs_f64();
+        if secs <= 0.0 {
+            return 0.0;
+        }
+        self.total_rays() as f64 / secs
+    }
+
+    pub fn average_path_length(&self) -> f64 {
+        // Average number of ray segments traced per camera ray
+        Self::ratio(self.total_rays(), self.primary_rays)
+    }
+
+    pub fn bvh_node_tests_per_ray(&self) -> f64 {
+        Self::ratio(self.bvh_node_tests, self.total_rays())
+    }
+
+    pub fn primitive_tests_per_ray(&self) -> f64 {
+        Self::ratio(self.primitive_tests, self.total_rays())
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            concat!(
+                "{{\n",
+                "  \"primary_rays\": {},\n",
+                "  \"secondary_rays\": {},\n",
+                "  \"rays_per_second\": {:.1},\n",
+                "  \"average_path_length\": {:.4},\n",
+                "  \"bvh_node_tests_per_ray\": {:.4},\n",
+                "  \"primitive_tests_per_ray\": {:.4},\n",
+                "  \"bvh_build_seconds\": {:.6},\n",
+                "  \"bvh_nodes\": {},\n",
+                "  \"bvh_bytes\": {},\n",
+                "  \"render_seconds\": {:.6},\n",
+                "  \"export_seconds\": {:.6}\n",
+                "}}\n"
+            ),
+            self.primary_rays,
+            self.secondary_rays,
+            self.rays_per_second(),
+            self.average_path_length(),
+            self.bvh_node_tests_per_ray(),
+            self.primitive_tests_per_ray(),
+            self.bvh_build_time.as_secs_f64(),
+            self.bvh_nodes,
+            self.bvh_bytes,
+            self.render_time.as_secs_f64(),
+            self.export_time.as_secs_f64(),
+        )
+    }
+
+    fn ratio(num: u64, den: u64) -> f64 {
+        if den == 0 {
+            0.0
+        } else {
+            num as f64 / den as f64
+        }
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Render statistics")?;
+        writeln!(f, "  Rays")?;
+        writeln!(f, "    primary             : {}", self.primary_rays)?;
+        writeln!(f, "    secondary           : {}", self.secondary_rays)?;
+        writeln!(f, "    rays / second       : {:.0}", self.rays_per_second())?;
+        writeln!(
+            f,
+            "    average path length : {:.3}",
+            self.average_path_length()
+        )?;
+        writeln!(f, "  Intersection tests per ray")?;
+        writeln!(
+            f,
+            "    BVH nodes           : {:.3}",
+            self.bvh_node_tests_per_ray()
+        )?;
+        writeln!(
+            f,
+            "    primitives          : {:.3}",
+            self.primitive_tests_per_ray()
+        )?;
+        writeln!(f, "  BVH")?;
+        writeln!(f, "    nodes               : {}", self.bvh_nodes)?;
+        writeln!(
+            f,
+            "    memory              : {:.1} KiB",
+            self.bvh_bytes as f64 / 1024.0
+        )?;
+        writeln!(f, "  Time")?;
+        writeln!(f, "    BVH build           : {:?}", self.bvh_build_time)?;
+        writeln!(f, "    render              : {:?}", self.render_time)?;
+        write!(f, "    export              : {:?}", self.export_time)
+    }
+}
@@ -0,0 +1,394 @@
+use std::{collections::HashMap, fs, io, path::Path, rc::Rc};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{
+    material::Material,
+    mesh::{TriangleMesh, triangulate},
+    vec3::{Point3, Vec3},
+};
+
+// How an STL file is turned into a mesh. STL has no units, materials or placement of its own, so
+// parts usually need scaling (CAD tends to work in millimeters) and moving into the scene.
+#[derive(Debug, Clone)]
+pub struct StlOptions {
+    // Multiplies every coordinate, e.g. 0.001 for a part modelled in millimeters to be in meters
+    pub scale: f64,
+    // CAD packages mostly use Z as up; this turns such parts to stand on our Y up ground
+    pub z_up: bool,
+    // None keeps the flat facets. Some(angle) smooths normals across edges where neighbouring
+    // facets differ by less than `angle` degrees, keeping sharper edges crisp.
+    pub smooth_angle: Option<f64>,
+    // Moves the part's bounding box center onto the origin in X and Z (and Y, without `ground`)
+    pub center: bool,
+    // Moves the part so its lowest point rests at this height
+    pub ground: Option<f64>,
+}
+
+impl Default for StlOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            z_up: false,
+            smooth_angle: None,
+            center: false,
+            ground: None,
+        }
+    }
+}
+
+// Loads an ASCII or binary STL file. Vertices shared between facets are welded, so a part's
+// triangles share one set of positions (and normals, when smoothed).
+#[allow(dead_code)]
+pub fn load_stl<P: AsRef<Path>>(
+    path: P,
+    mat: Rc<dyn Material>,
+    options: &StlOptions,
+) -> io::Result<TriangleMesh> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+    let facets = parse_stl(&bytes).map_err(|message| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {message}", path.display()),
+        )
+    })?;
+    Ok(build_mesh(facets, mat, options))
+}
+
+// One triangle, with the normal stored in the file (zero when the file left it out)
+struct Facet {
+    normal: Vec3,
+    vertices: [Point3; 3],
+}
+
+fn parse_stl(bytes: &[u8]) -> Result<Vec<Facet>, String> {
+    // Binary files have an 80 byte header, which may well start with "solid" too, so the size
+    // implied by the facet count decides
+    if bytes.len() >= 84 {
+        let count = LittleEndian::read_u32(&bytes[80..84]) as usize;
+        if bytes.len() == 84 + count * 50 {
+            return Ok(parse_binary(&bytes[84..], count));
+        }
+    }
+    if bytes.starts_with(b"solid") {
+        let text = String::from_utf8_lossy(bytes);
+        return parse_ascii(&text);
+    }
+    Err("Not an STL file (or a truncated binary one)".to_string())
+}
+
+fn parse_binary(data: &[u8], count: usize) -> Vec<Facet> {
+    // Each facet is a normal and three vertices as 12 floats, then a 2 byte attribute
+    let read_vec3 = |bytes: &[u8]| {
+        Vec3::new(
+            LittleEndian::read_f32(&bytes[0..4]) as f64,
+            LittleEndian::read_f32(&bytes[4..8]) as f64,
+            LittleEndian::read_f32(&bytes[8..12]) as f64,
+        )
+    };
+    (0..count)
+        .map(|index| {
+            let facet = &data[index * 50..index * 50 + 50];
+            Facet {
+                normal: read_vec3(&facet[0..12]),
+                vertices: [
+                    read_vec3(&facet[12..24]),
+                    read_vec3(&facet[24..36]),
+                    read_vec3(&facet[36..48]),
+                ],
+            }
+        })
+        .collect()
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<Facet>, String> {
+    // solid NAME / facet normal X Y Z / outer loop / vertex X Y Z ... / endloop / endfacet /
+    // endsolid NAME. Loops with more than three vertices are triangulated.
+    let mut facets = Vec::new();
+    let mut normal = Vec3::default();
+    let mut polygon: Vec<Point3> = Vec::new();
+    // A binary file cut short falls through to here when its header starts with "solid", and
+    // has none of the keywords
+    let mut recognized = false;
+
+    for (number, line) in text.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let vector = |args: &[&str]| -> Result<Vec3, String> {
+            let values = args
+                .iter()
+                .map(|arg| arg.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| format!("line {}: Bad number", number + 1))?;
+            match values.as_slice() {
+                [x, y, z] => Ok(Vec3::new(*x, *y, *z)),
+                _ => Err(format!("line {}: Expected three numbers", number + 1)),
+            }
+        };
+
+        match tokens.as_slice() {
+            ["facet", "normal", args @ ..] => {
+                recognized = true;
+                normal = vector(args)?;
+                polygon.clear();
+            }
+            ["vertex", args @ ..] => polygon.push(vector(args)?),
+            ["endfacet", ..] => {
+                for [a, b, c] in triangulate(&polygon) {
+                    facets.push(Facet {
+                        normal,
+                        vertices: [polygon[a], polygon[b], polygon[c]],
+                    });
+                }
+                normal = Vec3::default();
+                polygon.clear();
+            }
+            ["endsolid", ..] => recognized = true,
+            _ => {}
+        }
+    }
+    if !recognized {
+        return Err("Not an STL file (or a truncated binary one)".to_string());
+    }
+    Ok(facets)
+}
+
+fn build_mesh(facets: Vec<Facet>, mat: Rc<dyn Material>, options: &StlOptions) -> TriangleMesh {
+    let place = |point: Point3| {
+        let point = point * options.scale;
+        if options.z_up {
+            Point3::new(point.x(), point.z(), -point.y())
+        } else {
+            point
+        }
+    };
+
+    let mut triangles: Vec<[Point3; 3]> = Vec::with_capacity(facets.len());
+    for facet in facets {
+        let mut vertices = facet.vertices.map(place);
+        // The winding is what counts for us, but where the file's normal disagrees with it the
+        // normal is more likely to be right
+        let edge1 = vertices[1] - &vertices[0];
+        let edge2 = vertices[2] - &vertices[0];
+        let geometric = edge1.cross(&edge2);
+        let stored = if options.z_up {
+            Vec3::new(facet.normal.x(), facet.normal.z(), -facet.normal.y())
+        } else {
+            facet.normal
+        };
+        if geometric.dot(&stored) < 0.0 {
+            vertices.swap(1, 2);
+        }
+        // Slivers and collapsed facets are dropped. The cross product's length is relative to
+        // the edges (it's their lengths times the sine of the angle between them), so small
+        // facets of a scaled down part are kept.
+        let sin_squared = geometric.len_squared() / (edge1.len_squared() * edge2.len_squared());
+        if sin_squared > 1e-16 {
+            triangles.push(vertices);
+        }
+    }
+
+    // Centering and grounding go by the bounding box of the placed part
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for point in triangles.iter().flatten() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(point[axis]);
+            max[axis] = max[axis].max(point[axis]);
+        }
+    }
+    let mut offset = Vec3::default();
+    if !triangles.is_empty() {
+        if options.center {
+            offset = Vec3::new(min[0] + max[0], min[1] + max[1], min[2] + max[2]) * -0.5;
+        }
+        if let Some(ground) = options.ground {
+            offset = Vec3::new(offset.x(), ground - min[1], offset.z());
+        }
+    }
+
+    // Vertices are welded by position, and with smoothing also by normal, so creases keep a
+    // separate vertex per side
+    let key = |point: &Point3| [point.x(), point.y(), point.z()].map(f64::to_bits);
+    let face_normals: Vec<Vec3> = triangles
+        .iter()
+        .map(|[a, b, c]| (*b - a).cross(&(*c - a)))
+        .collect();
+    let mut incident: HashMap<[u64; 3], Vec<usize>> = HashMap::new();
+    if options.smooth_angle.is_some() {
+        for (index, triangle) in triangles.iter().enumerate() {
+            for point in triangle {
+                incident.entry(key(point)).or_default().push(index);
+            }
+        }
+    }
+    let min_cos = options
+        .smooth_angle
+        .map(|angle| angle.to_radians().cos())
+        .unwrap_or(1.0);
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::with_capacity(triangles.len());
+    let mut welded: HashMap<([u64; 3], [u64; 3]), u32> = HashMap::new();
+    for (index, triangle) in triangles.iter().enumerate() {
+        let own_normal = face_normals[index].unit_vector();
+        let mut corner = |point: &Point3| -> u32 {
+            // Area weighted average over the facets around this corner that are close enough
+            // in angle to this one
+            let normal = match incident.get(&key(point)) {
+                Some(faces) => {
+                    let mut sum = Vec3::default();
+                    for &face in faces {
+                        if face_normals[face].unit_vector().dot(&own_normal) >= min_cos {
+                            sum += &face_normals[face];
+                        }
+                    }
+                    sum.unit_vector()
+                }
+                None => Vec3::default(),
+            };
+            let vertex_key = (key(point), key(&normal));
+            *welded.entry(vertex_key).or_insert_with(|| {
+                positions.push(*point + &offset);
+                normals.push(normal);
+                (positions.len() - 1) as u32
+            })
+        };
+        indices.push([
+            corner(&triangle[0]),
+            corner(&triangle[1]),
+            corner(&triangle[2]),
+        ]);
+    }
+
+    let mesh = TriangleMesh::new(positions, indices, mat);
+    if options.smooth_angle.is_some() {
+        mesh.with_normals(normals)
+    } else {
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{material::Lambertian, vec3::Color};
+
+    // A unit square in the XY plane, as two facets
+    const SQUARE: [[[f32; 3]; 3]; 2] = [
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+        [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+    ];
+
+    fn binary(header: &[u8]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(80, 0);
+        bytes.extend((SQUARE.len() as u32).to_le_bytes());
+        for facet in SQUARE {
+            bytes.extend([0.0f32, 0.0, 1.0].map(f32::to_le_bytes).as_flattened());
+            bytes.extend(
+                facet
+                    .as_flattened()
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes()),
+            );
+            bytes.extend([0, 0]);
+        }
+        bytes
+    }
+
+    fn load(name: &str, contents: &[u8], options: &StlOptions) -> io::Result<TriangleMesh> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let mat = Rc::new(Lambertian::from_color(Color::default()));
+        let mesh = load_stl(&path, mat, options);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn loads_ascii_and_binary_files() {
+        let mut ascii = "solid square\n".to_string();
+        for facet in SQUARE {
+            ascii += "  facet normal 0 0 1\n    outer loop\n";
+            for [x, y, z] in facet {
+                ascii += &format!("      vertex {x} {y} {z}\n");
+            }
+            ascii += "    endloop\n  endfacet\n";
+        }
+        ascii += "endsolid square\n";
+        let options = StlOptions::default();
+        let mesh = load("rusttracer_ascii.stl", ascii.as_bytes(), &options).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        // Binary headers starting with "solid" are told apart by their size
+        for header in [&b"binary square"[..], b"solid square"] {
+            let mesh = load("rusttracer_binary.stl", &binary(header), &options).unwrap();
+            assert_eq!(mesh.triangle_count(), 2);
+        }
+    }
+
+    #[test]
+    fn keeps_small_facets_of_scaled_parts() {
+        // A millimeter square scaled to meters has tiny cross products, but isn't degenerate
+        let mut bytes = binary(b"");
+        for value in bytes[84..]
+            .chunks_exact_mut(50)
+            .flat_map(|facet| facet[12..48].chunks_exact_mut(4))
+        {
+            let scaled = f32::from_le_bytes(value.try_into().unwrap()) * 0.01;
+            value.copy_from_slice(&scaled.to_le_bytes());
+        }
+        let options = StlOptions {
+            scale: 0.001,
+            ..Default::default()
+        };
+        let mesh = load("rusttracer_small.stl", &bytes, &options).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        // Collapsed facets are still dropped
+        let mut bytes = binary(b"");
+        bytes.copy_within(84 + 24..84 + 36, 84 + 36);
+        let mesh = load("rusttracer_collapsed.stl", &bytes, &options).unwrap();
+        assert_eq!(mesh.triangle_count(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let options = StlOptions::default();
+        let mut truncated = binary(b"");
+        truncated.truncate(truncated.len() - 10);
+        let err = load("rusttracer_truncated.stl", &truncated, &options)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Not an STL file"), "{err}");
+
+        // Also when the header makes it look like an ASCII file
+        let mut truncated = binary(b"solid square");
+        truncated.truncate(truncated.len() - 10);
+        let err = load("rusttracer_truncated_solid.stl", &truncated, &options)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Not an STL file"), "{err}");
+
+        // An ASCII file without facets is still a valid, empty one
+        let empty = load(
+            "rusttracer_empty.stl",
+            b"solid empty\nendsolid empty\n",
+            &options,
+        );
+        assert_eq!(empty.unwrap().triangle_count(), 0);
+
+        let ascii = "solid bad\nfacet normal 0 0 1\nouter loop\nvertex 0 0 zero\n";
+        let err = load("rusttracer_bad.stl", ascii.as_bytes(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 4: Bad number"), "{err}");
+    }
+}
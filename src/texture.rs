@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{io, rc::Rc};
 
 use crate::{
     interval::Interval,
@@ -83,9 +83,28 @@ pub struct ImageTexture {
 impl ImageTexture {
     #[allow(dead_code)]
     pub fn new(path: &str) -> Self {
-        Self {
-            image: RTWImage::new(path).unwrap(),
+        Self::load(path).unwrap()
+    }
+
+    #[allow(dead_code)]
+    pub fn load(path: &str) -> io::Result<Self> {
+        // Like `new`, but reports a missing or unreadable image instead of panicking
+        let image = RTWImage::new(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{err}: {path}")))?;
+        Ok(Self { image })
+    }
+
+    #[allow(dead_code)]
+    pub fn from_memory(bytes: &[u8]) -> io::Result<Self> {
+        // Decodes an image file's contents, for images embedded in other files
+        let mut image = RTWImage::default();
+        if !image.load_from_memory(bytes) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Couldn't decode embedded image",
+            ));
         }
+        Ok(Self { image })
     }
 }
 
@@ -0,0 +1,154 @@
+use std::{f64::consts::PI, rc::Rc};
+
+use crate::{
+    aabb::AABB,
+    disk::Disk,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    polynomial::solve_quartic,
+    ray::Ray,
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+// Torus around `axis` through `center`: the surface swept by a circle of the minor radius whose
+// center goes around a circle of the major radius. A minor radius above the major one gives a
+// self-intersecting "spindle" torus, which still renders correctly.
+//
+// Texture coordinates are u around the axis and v around the tube, starting from its inside.
+pub struct Torus {
+    center: Point3,
+    // Local frame, `w` pointing along the axis
+    u_axis: Vec3,
+    v_axis: Vec3,
+    w_axis: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Torus {
+    #[allow(dead_code)]
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        let w_axis = axis.unit_vector();
+        let (u_axis, v_axis) = w_axis.orthonormal_basis();
+        let major_radius = major_radius.max(0.0);
+        let minor_radius = minor_radius.max(0.0);
+
+        // The ring's bounds grown by the tube's radius on every side, which is exact
+        let ring = Disk::bounds(center, w_axis, major_radius);
+        let bbox = AABB::new(
+            ring.x.expand(2.0 * minor_radius),
+            ring.y.expand(2.0 * minor_radius),
+            ring.z.expand(2.0 * minor_radius),
+        );
+
+        Self {
+            center,
+            u_axis,
+            v_axis,
+            w_axis,
+            major_radius,
+            minor_radius,
+            mat,
+            bbox,
+        }
+    }
+
+    fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            v.dot(&self.u_axis),
+            v.dot(&self.v_axis),
+            v.dot(&self.w_axis),
+        )
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        self.u_axis * v.x() + &(self.v_axis * v.y()) + &(self.w_axis * v.z())
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        stats::record_primitive_test();
+
+        let big_r = self.major_radius;
+        let small_r = self.minor_radius;
+
+        // Work in the local frame with the torus centered at the origin and its axis along z.
+        // The quartic's coefficients grow with the fourth power of the distance to the torus,
+        // so restart the ray from its point closest to the center and use a unit direction,
+        // keeping them small. `s` below is the distance along the ray from that point.
+        let dir = self.to_local(&ray.dir);
+        let dir_len = dir.len();
+        let dir = dir / dir_len;
+        let origin = self.to_local(&(ray.origin - &self.center));
+        let t_closest = -origin.dot(&dir) / dir_len;
+        let origin = origin + &(dir * (t_closest * dir_len));
+
+        // Rays passing outside the bounding sphere can't hit
+        let outer = big_r + small_r;
+        if origin.len_squared() > outer * outer {
+            return false;
+        }
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (px^2 + py^2), with p = origin + s dir
+        let g = origin.dot(&dir);
+        let k = origin.len_squared() + big_r * big_r - small_r * small_r;
+        let four_r2 = 4.0 * big_r * big_r;
+        let dxy = dir.x() * dir.x() + dir.y() * dir.y();
+        let oxy = origin.x() * dir.x() + origin.y() * dir.y();
+        let oo = origin.x() * origin.x() + origin.y() * origin.y();
+        let roots = solve_quartic(
+            4.0 * g,
+            4.0 * g * g + 2.0 * k - four_r2 * dxy,
+            4.0 * g * k - 2.0 * four_r2 * oxy,
+            k * k - four_r2 * oo,
+        );
+
+        let mut closest = ray_t.max;
+        let mut hit_s = None;
+        for s in roots {
+            let t = t_closest + s / dir_len;
+            if ray_t.surrounds(t) && t < closest {
+                closest = t;
+                hit_s = Some(s);
+            }
+        }
+        let Some(s) = hit_s else {
+            return false;
+        };
+
+        // The outward normal points away from the nearest point on the ring
+        let p = origin + &(dir * s);
+        let ring_distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let ring_point = if ring_distance > 0.0 {
+            Vec3::new(p.x(), p.y(), 0.0) * (big_r / ring_distance)
+        } else {
+            Vec3::default()
+        };
+        let local_normal = (p - &ring_point).unit_vector();
+
+        rec.u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        rec.v = (p.z().atan2(ring_distance - big_r) + PI) / (2.0 * PI);
+
+        rec.t = closest;
+        rec.p = ray.at(closest);
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(ray, self.to_world(&local_normal));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
@@ -0,0 +1,191 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix3, Matrix4, Unit, UnitQuaternion, Vector3, Vector4};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    utils::degrees_to_radians,
+    vec3::{Point3, Vec3},
+};
+
+// Places an object with an arbitrary affine transform: any mix of translation, rotation about
+// any axis, non-uniform scale and shear. Rays are moved into object space with the inverse
+// matrix, and hits moved back out, with normals going through the inverse transpose so they stay
+// perpendicular to sheared or unevenly scaled surfaces.
+//
+// The builder methods each apply a transform after the ones before, so
+// `Transform::new(object).scale(..).rotate(..).translate(..)` scales first and moves last.
+pub struct Transform {
+    object: Rc<dyn Hittable>,
+    matrix: Matrix4<f64>,
+    inverse: Matrix4<f64>,
+    normal_matrix: Matrix3<f64>,
+    bbox: AABB,
+}
+
+impl Transform {
+    #[allow(dead_code)]
+    pub fn new(object: Rc<dyn Hittable>) -> Self {
+        Self::from_matrix(object, Matrix4::identity())
+    }
+
+    #[allow(dead_code)]
+    pub fn from_matrix(object: Rc<dyn Hittable>, matrix: Matrix4<f64>) -> Self {
+        // The matrix must be affine (bottom row 0 0 0 1) and invertible
+        let inverse = matrix
+            .try_inverse()
+            .expect("Transform matrix must be invertible!");
+        let linear: Matrix3<f64> = matrix.fixed_view::<3, 3>(0, 0).into();
+        let normal_matrix = linear
+            .try_inverse()
+            .expect("Transform matrix must be invertible!")
+            .transpose();
+        let bbox = Self::transform_bbox(&matrix, object.bounding_box());
+        Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix,
+            bbox,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn then(self, matrix: Matrix4<f64>) -> Self {
+        // Applies `matrix` after the current transform
+        Self::from_matrix(self.object, matrix * self.matrix)
+    }
+
+    #[allow(dead_code)]
+    pub fn translate(self, offset: Vec3) -> Self {
+        self.then(translation(offset))
+    }
+
+    #[allow(dead_code)]
+    pub fn rotate(self, axis: Vec3, angle: f64) -> Self {
+        self.then(rotation(axis, angle))
+    }
+
+    #[allow(dead_code)]
+    pub fn scale(self, factors: Vec3) -> Self {
+        self.then(scaling(factors))
+    }
+
+    #[allow(dead_code)]
+    pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+        self.then(shearing(xy, xz, yx, yz, zx, zy))
+    }
+
+    #[allow(dead_code)]
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.matrix
+    }
+
+    pub fn transform_bbox(matrix: &Matrix4<f64>, bbox: AABB) -> AABB {
+        // Bounds of the eight transformed corners. Unbounded objects stay unbounded.
+        if !bbox.is_bounded() {
+            return AABB::universe();
+        }
+
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for i in 0..8 {
+            let corner = Vector4::new(
+                if i & 1 == 0 { bbox.x.min } else { bbox.x.max },
+                if i & 2 == 0 { bbox.y.min } else { bbox.y.max },
+                if i & 4 == 0 { bbox.z.min } else { bbox.z.max },
+                1.0,
+            );
+            let transformed = matrix * corner;
+            for c in 0..3 {
+                min[c] = min[c].min(transformed[c]);
+                max[c] = max[c].max(transformed[c]);
+            }
+        }
+        AABB::from_points(min, max)
+    }
+}
+
+// Matrices for the basic transforms, to be combined by multiplying them (the rightmost applies
+// first) for `Transform::from_matrix` or `Instance::new`
+
+#[allow(dead_code)]
+pub fn translation(offset: Vec3) -> Matrix4<f64> {
+    Matrix4::new_translation(&to_vector(offset))
+}
+
+#[allow(dead_code)]
+pub fn rotation(axis: Vec3, angle: f64) -> Matrix4<f64> {
+    // Rotation by `angle` degrees about `axis` (through the origin), counter-clockwise when
+    // looking down the axis
+    let axis = Unit::new_normalize(to_vector(axis));
+    UnitQuaternion::from_axis_angle(&axis, degrees_to_radians(angle)).to_homogeneous()
+}
+
+#[allow(dead_code)]
+pub fn scaling(factors: Vec3) -> Matrix4<f64> {
+    Matrix4::new_nonuniform_scaling(&to_vector(factors))
+}
+
+#[allow(dead_code)]
+pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix4<f64> {
+    // Each coordinate gains a multiple of the others, e.g. x += xy * y + xz * z
+    #[rustfmt::skip]
+    let shear = Matrix4::new(
+        1.0, xy, xz, 0.0,
+        yx, 1.0, yz, 0.0,
+        zx, zy, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    );
+    shear
+}
+
+fn to_vector(v: Vec3) -> Vector3<f64> {
+    Vector3::new(v.x(), v.y(), v.z())
+}
+
+fn transform_point(matrix: &Matrix4<f64>, p: Point3) -> Point3 {
+    let p = matrix * Vector4::new(p.x(), p.y(), p.z(), 1.0);
+    Point3::new(p.x, p.y, p.z)
+}
+
+fn transform_vector(matrix: &Matrix4<f64>, v: Vec3) -> Vec3 {
+    let v = matrix * Vector4::new(v.x(), v.y(), v.z(), 0.0);
+    Vec3::new(v.x, v.y, v.z)
+}
+
+fn transform_normal(normal_matrix: &Matrix3<f64>, n: Vec3) -> Vec3 {
+    let n = normal_matrix * to_vector(n);
+    Vec3::new(n.x, n.y, n.z).unit_vector()
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Transform the ray into object space. The direction isn't renormalized, so the hit
+        // distance t is the same in both spaces.
+        let object_ray = Ray::new(
+            transform_point(&self.inverse, ray.origin),
+            transform_vector(&self.inverse, ray.dir),
+            ray.time,
+        );
+
+        if !self.object.hit(&object_ray, ray_t, rec) {
+            return false;
+        }
+
+        // Move the intersection back out to world space. Normals keep facing the ray, since the
+        // inverse transpose preserves their dot products with transformed directions.
+        rec.p = transform_point(&self.matrix, rec.p);
+        rec.normal = transform_normal(&self.normal_matrix, rec.normal);
+        rec.geometric_normal = transform_normal(&self.normal_matrix, rec.geometric_normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
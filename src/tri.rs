@@ -2,51 +2,53 @@ use std::rc::Rc;
 
 use crate::{
     aabb::AABB,
-    hittable::{self, HitRecord, Hittable},
-    interval::{self, Interval},
+    hittable::HitRecord,
+    interval::Interval,
     material::Material,
-    ray,
+    planar::{Planar, PlanarShape},
     vec3::{Point3, Vec3},
 };
 
-pub struct Tri {
-    q: Point3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    mat: Rc<dyn Material>,
-    bbox: AABB,
-    normal: Vec3,
-    d: f64,
+pub type Tri = Planar<Triangle>;
+
+// The triangle with corners `q`, `q + u` and `q + v`
+#[derive(Default)]
+pub struct Triangle {
+    // Optional per vertex normals and texture coordinates, for the vertices at q, q + u and
+    // q + v, interpolated across the triangle
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[[f64; 2]; 3]>,
 }
 
 impl Tri {
     #[allow(dead_code)]
     pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Self {
-        let n = u.cross(&v);
-        let normal = n.unit_vector();
-        Self {
-            q,
-            u,
-            v,
-            w: n / n.dot(&n),
-            mat,
-            bbox: Self::compute_bbox(q, u, v),
-            normal,
-            d: normal.dot(&q),
-        }
+        Self::from_shape(Triangle::default(), q, u, v, mat)
+    }
+
+    #[allow(dead_code)]
+    pub fn from_vertices(p0: Point3, p1: Point3, p2: Point3, mat: Rc<dyn Material>) -> Self {
+        Self::new(p0, p1 - &p0, p2 - &p0, mat)
     }
 
     #[allow(dead_code)]
-    fn compute_bbox(q: Point3, u: Vec3, v: Vec3) -> AABB {
-        // Compute the bounding box of all four vertices
-        let bbox_diag_1 = AABB::from_points(q, q + &u + &v);
-        let bbox_diag_2 = AABB::from_points(q + &u, q + &v);
-        AABB::from_aabbs(bbox_diag_1, bbox_diag_2)
+    pub fn with_normals(mut self, n0: Vec3, n1: Vec3, n2: Vec3) -> Self {
+        // Smooth shading: the normal at a hit is interpolated from these. Which side was hit is
+        // still decided by the flat normal.
+        self.shape.normals = Some([n0.unit_vector(), n1.unit_vector(), n2.unit_vector()]);
+        self
     }
 
     #[allow(dead_code)]
-    fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
+    pub fn with_uvs(mut self, uv0: [f64; 2], uv1: [f64; 2], uv2: [f64; 2]) -> Self {
+        // Texture coordinates, replacing the barycentric coordinates as the hit's u and v
+        self.shape.uvs = Some([uv0, uv1, uv2]);
+        self
+    }
+}
+
+impl PlanarShape for Triangle {
+    fn is_interior(&self, a: f64, b: f64, rec: &mut HitRecord) -> bool {
         let unit_interval = Interval::new(0.0, 1.0);
         // Given the hit point in plane coordinates, return false if it is outside the
         // primitive, otherwise set the hit record UV coordinates and return true.
@@ -63,48 +65,24 @@ impl Tri {
         rec.v = b;
         return true;
     }
-}
 
-impl Hittable for Tri {
-    fn hit(
-        &self,
-        ray: &ray::Ray,
-        ray_t: interval::Interval,
-        rec: &mut hittable::HitRecord,
-    ) -> bool {
-        let denom = self.normal.dot(&ray.dir);
-
-        // No hit if the ray is parallel to the plane.
-        if denom.abs() < 1e-8 {
-            return false;
-        }
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> AABB {
+        // Compute the bounding box of all three vertices
+        AABB::from_aabbs(AABB::from_points(q, q + &u), AABB::from_points(q, q + &v))
+    }
 
-        // Return false if the hit point parameter t is outside the ray interval
-        let t = (self.d - self.normal.dot(&ray.origin)) / denom;
-        if !ray_t.contains(t) {
-            return false;
+    fn finish_hit(&self, rec: &mut HitRecord) {
+        // Barycentric weights of the vertices at q, q + u and q + v
+        let (alpha, beta) = (rec.u, rec.v);
+        let weights = [1.0 - alpha - beta, alpha, beta];
+        if let Some(normals) = &self.normals {
+            let normal =
+                normals[0] * weights[0] + &(normals[1] * weights[1]) + &(normals[2] * weights[2]);
+            rec.set_shading_normal(normal.unit_vector());
         }
-
-        // Determine if the hit point lies within the planar shape using its plane coordinates
-        let intersection = ray.at(t);
-        let planar_hitpt_vec = intersection - &self.q;
-        let beta = self.w.dot(&planar_hitpt_vec.cross(&self.v));
-        let alpha = self.w.dot(&self.u.cross(&planar_hitpt_vec));
-
-        if !Self::is_interior(alpha, beta, rec) {
-            return false;
+        if let Some(uvs) = &self.uvs {
+            rec.u = weights[0] * uvs[0][0] + weights[1] * uvs[1][0] + weights[2] * uvs[2][0];
+            rec.v = weights[0] * uvs[0][1] + weights[1] * uvs[1][1] + weights[2] * uvs[2][1];
         }
-
-        // Ray hits the 2D shape; set the rest of the hit record and return true
-        rec.t = t;
-        rec.p = intersection;
-        rec.mat = Some(self.mat.clone());
-        rec.set_face_normal(ray, self.normal);
-
-        return true;
-    }
-
-    fn bounding_box(&self) -> AABB {
-        self.bbox
     }
 }
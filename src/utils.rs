@@ -11,6 +11,13 @@ lazy_static! {
     };
 }
 
+#[allow(dead_code)]
+pub fn seed_rng(seed: u64) {
+    // Restarts the global RNG from a fixed seed, making scene construction and rendering
+    // reproducible
+    *RNG.lock().expect("Failed to lock the global RNG Mutex") = SmallRng::seed_from_u64(seed);
+}
+
 #[allow(dead_code)]
 #[inline(always)]
 pub fn degrees_to_radians(degrees: f64) -> f64 {
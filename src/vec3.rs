@@ -77,6 +77,13 @@ impl Vec3 {
         self.0.magnitude_squared()
     }
 
+    #[allow(dead_code)]
+    #[inline]
+    pub fn luminance(&self) -> f64 {
+        // Relative luminance of a linear RGB color (Rec. 709 weights)
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
+
     // map generators
 
     #[allow(dead_code)]
@@ -133,6 +140,23 @@ impl Vec3 {
         }
     }
 
+    #[allow(dead_code)]
+    #[inline]
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3) {
+        // Two unit vectors perpendicular to this (unit) vector and each other, such that
+        // u x v = self (Duff et al., "Building an Orthonormal Basis, Revisited")
+        let sign = 1.0_f64.copysign(self.z());
+        let a = -1.0 / (sign + self.z());
+        let b = self.x() * self.y() * a;
+        let u = Vec3::new(
+            1.0 + sign * self.x() * self.x() * a,
+            sign * b,
+            -sign * self.x(),
+        );
+        let v = Vec3::new(b, sign + self.y() * self.y() * a, -self.y());
+        (u, v)
+    }
+
     // static functions
 
     #[allow(dead_code)]